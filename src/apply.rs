@@ -0,0 +1,236 @@
+#[cfg(feature = "std")]
+use std::error::Error;
+
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt;
+
+use DiffComponent;
+use hunk::Hunk;
+
+/// The reason a diff could not be applied to (or unapplied from) a sequence.
+///
+/// `index` is always a position in the sequence the diff was applied to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApplyError {
+    /// The element at `index` is not the one the diff expects to keep or remove there.
+    Mismatch { index: usize },
+
+    /// The diff expects more elements than the sequence has; `index` is the sequence's length.
+    UnexpectedEnd { index: usize },
+
+    /// The diff ends before the sequence does; `index` is the first element it doesn't cover.
+    TrailingElements { index: usize },
+
+    /// A hunk starts at `index`, before the end of the previous hunk.
+    OverlappingHunk { index: usize }
+}
+
+impl fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ApplyError::Mismatch { index } => {
+                write!(f, "element {} does not match the diff", index)
+            },
+            ApplyError::UnexpectedEnd { index } => {
+                write!(f, "diff expects more than {} elements", index)
+            },
+            ApplyError::TrailingElements { index } => {
+                write!(f, "diff ends before element {}", index)
+            },
+            ApplyError::OverlappingHunk { index } => {
+                write!(f, "hunk at element {} overlaps the previous hunk", index)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for ApplyError {}
+
+/// Applies a diff from `a` to some `b` to `a`, returning `b`. Every element the diff keeps or
+/// deletes is checked against the corresponding element of `a`, and the diff must account for all
+/// of `a`.
+///
+/// ```
+/// let a: Vec<_> = "AaaxZ".chars().collect();
+/// let b: Vec<_> = "AxaaZ".chars().collect();
+/// let diff = patience_diff::patience_diff(&a, &b);
+///
+/// assert_eq!(patience_diff::apply(&a, &diff), Ok(b.clone()));
+/// assert_eq!(patience_diff::unapply(&b, &diff), Ok(a.clone()));
+///
+/// let c: Vec<_> = "AxxxZ".chars().collect();
+/// assert_eq!(patience_diff::apply(&c, &diff),
+///            Err(patience_diff::ApplyError::Mismatch { index: 1 }));
+/// ```
+pub fn apply<T, U>(a: &[T], diff: &[DiffComponent<U>]) -> Result<Vec<T>, ApplyError>
+        where T: Clone + PartialEq, U: Borrow<T> {
+    let mut ret = Vec::with_capacity(a.len());
+    let index = apply_at(a, 0, diff, &mut ret)?;
+
+    if index < a.len() {
+        return Err(ApplyError::TrailingElements { index });
+    }
+
+    Ok(ret)
+}
+
+/// Applies hunks, such as those returned by `hunks` or `parse_patch`, to `a`. Elements of `a`
+/// that come before, between or after the hunks are kept as they are. The hunks must be sorted
+/// and must not overlap.
+pub fn apply_hunks<T, U>(a: &[T], hunks: &[Hunk<U>]) -> Result<Vec<T>, ApplyError>
+        where T: Clone + PartialEq, U: Borrow<T> {
+    let mut ret = Vec::with_capacity(a.len());
+    let mut index = 0;
+
+    for hunk in hunks {
+        if hunk.old_start < index {
+            return Err(ApplyError::OverlappingHunk { index: hunk.old_start });
+        }
+
+        if hunk.old_start > a.len() {
+            return Err(ApplyError::UnexpectedEnd { index: a.len() });
+        }
+
+        ret.extend_from_slice(&a[index..hunk.old_start]);
+        index = apply_at(a, hunk.old_start, &hunk.components, &mut ret)?;
+    }
+
+    ret.extend_from_slice(&a[index..]);
+    Ok(ret)
+}
+
+/// Reverts a diff from some `a` to `b`, returning `a`. This is `apply` in the opposite direction:
+/// every element the diff keeps or inserts is checked against the corresponding element of `b`.
+pub fn unapply<T, U>(b: &[T], diff: &[DiffComponent<U>]) -> Result<Vec<T>, ApplyError>
+        where T: Clone + PartialEq, U: Borrow<T> {
+    let mut ret = Vec::with_capacity(b.len());
+    let mut index = 0;
+
+    for component in diff {
+        match *component {
+            DiffComponent::Insertion(ref elem_b) => {
+                check(b, index, elem_b.borrow())?;
+                index += 1;
+            },
+            DiffComponent::Unchanged(ref elem_a, ref elem_b) => {
+                check(b, index, elem_b.borrow())?;
+                ret.push(elem_a.borrow().clone());
+                index += 1;
+            },
+            DiffComponent::Deletion(ref elem_a) => {
+                ret.push(elem_a.borrow().clone());
+            }
+        }
+    }
+
+    if index < b.len() {
+        return Err(ApplyError::TrailingElements { index });
+    }
+
+    Ok(ret)
+}
+
+/// Applies `diff` to `a`, starting at `index`, and pushes the result onto `ret`. Returns the
+/// position in `a` right after the last element the diff covers.
+fn apply_at<T, U>(a: &[T], mut index: usize, diff: &[DiffComponent<U>], ret: &mut Vec<T>)
+        -> Result<usize, ApplyError> where T: Clone + PartialEq, U: Borrow<T> {
+    for component in diff {
+        match *component {
+            DiffComponent::Insertion(ref elem_b) => {
+                ret.push(elem_b.borrow().clone());
+            },
+            DiffComponent::Unchanged(ref elem_a, ref elem_b) => {
+                check(a, index, elem_a.borrow())?;
+                ret.push(elem_b.borrow().clone());
+                index += 1;
+            },
+            DiffComponent::Deletion(ref elem_a) => {
+                check(a, index, elem_a.borrow())?;
+                index += 1;
+            }
+        }
+    }
+
+    Ok(index)
+}
+
+fn check<T>(elems: &[T], index: usize, expected: &T) -> Result<(), ApplyError>
+        where T: PartialEq {
+    match elems.get(index) {
+        Some(elem) if elem == expected => Ok(()),
+        Some(_) => Err(ApplyError::Mismatch { index }),
+        None => Err(ApplyError::UnexpectedEnd { index })
+    }
+}
+
+#[test]
+fn test_apply_round_trip() {
+    let empty: Vec<&str> = vec![];
+    let cases = vec![
+        (empty.clone(), vec!["x", "y"]),
+        (vec!["x", "y"], empty.clone()),
+        (vec!["x", "y", "z"], vec!["x", "z", "w"])
+    ];
+
+    for (a, b) in cases {
+        let diff = ::patience_diff(&a, &b);
+        assert_eq!(apply(&a, &diff), Ok(b.clone()));
+        assert_eq!(unapply(&b, &diff), Ok(a.clone()));
+
+        let owned: Vec<DiffComponent<&str>> = diff.iter().map(|c| {
+            match *c {
+                DiffComponent::Insertion(&elem_b) => DiffComponent::Insertion(elem_b),
+                DiffComponent::Unchanged(&elem_a, &elem_b) => {
+                    DiffComponent::Unchanged(elem_a, elem_b)
+                },
+                DiffComponent::Deletion(&elem_a) => DiffComponent::Deletion(elem_a)
+            }
+        }).collect();
+        assert_eq!(apply(&a, &owned), Ok(b));
+    }
+
+    // An insertion-only diff accounts for none of a non-empty input.
+    let inserted = ::patience_diff(&empty, &["x"]);
+    assert_eq!(apply(&["x"], &inserted), Err(ApplyError::TrailingElements { index: 0 }));
+    assert_eq!(unapply(&empty, &inserted), Err(ApplyError::UnexpectedEnd { index: 0 }));
+
+    // A deletion-only diff checks what it deletes, and produces nothing to unapply from.
+    let deleted = ::patience_diff(&["x", "y"], &empty);
+    assert_eq!(apply(&["x", "z"], &deleted), Err(ApplyError::Mismatch { index: 1 }));
+    assert_eq!(unapply(&["x"], &deleted), Err(ApplyError::TrailingElements { index: 0 }));
+
+    // The insertion at the end of a diff is checked when unapplying.
+    let diff = ::patience_diff(&["x", "y", "z"], &["x", "z", "w"]);
+    assert_eq!(unapply(&["x", "z"], &diff), Err(ApplyError::UnexpectedEnd { index: 2 }));
+    assert_eq!(unapply(&["x", "z", "v"], &diff), Err(ApplyError::Mismatch { index: 2 }));
+}
+
+#[test]
+fn test_apply_errors() {
+    let a = vec![1, 2, 3];
+    let b = vec![1, 3, 4];
+    let diff = ::patience_diff(&a, &b);
+
+    assert_eq!(apply(&[1, 2], &diff), Err(ApplyError::UnexpectedEnd { index: 2 }));
+    assert_eq!(apply(&[1, 2, 3, 5], &diff), Err(ApplyError::TrailingElements { index: 3 }));
+    assert_eq!(unapply(&[1, 3, 5], &diff), Err(ApplyError::Mismatch { index: 2 }));
+}
+
+#[test]
+fn test_apply_hunks() {
+    let a: Vec<_> = (0..20).collect();
+    let mut b = a.clone();
+    b[3] = 30;
+    b.insert(15, 150);
+
+    let diff = ::patience_diff(&a, &b);
+    let hunks = ::hunks(&diff, 2);
+    assert_eq!(hunks.len(), 2);
+    assert_eq!(apply_hunks(&a, &hunks), Ok(b.clone()));
+
+    let reversed = vec![hunks[1].clone(), hunks[0].clone()];
+    assert_eq!(apply_hunks(&a, &reversed), Err(ApplyError::OverlappingHunk { index: 1 }));
+    assert_eq!(apply_hunks(&a[..10], &hunks), Err(ApplyError::UnexpectedEnd { index: 10 }));
+}
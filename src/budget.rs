@@ -0,0 +1,116 @@
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+use alloc::vec::Vec;
+
+use DiffComponent;
+
+/// The clock is only read after this many units of work, since reading it is slower than most
+/// units of work.
+#[cfg(feature = "std")]
+const CLOCK_INTERVAL: u64 = 4096;
+
+/// Limits on how long `diff_within` and `diff_indices_within` may work for. The default has no
+/// limits.
+///
+/// A unit of work is roughly one comparison or hash of an element. Once either limit is reached,
+/// every range that is left to diff is diffed as the deletion of all of its elements from `a`
+/// followed by the insertion of all of its elements from `b`, which takes time linear in its
+/// length.
+///
+/// Which limits there are depends on the features this crate is built with, so a `Budget` is
+/// built from the default with `with_work` and `with_deadline`, rather than from its fields.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Budget {
+    /// The number of units of work to stop after.
+    pub work: Option<u64>,
+
+    /// The time to stop at. The clock is not checked after every unit of work, so this can be
+    /// overrun slightly. This needs the `std` feature.
+    #[cfg(feature = "std")]
+    pub deadline: Option<Instant>
+}
+
+impl Budget {
+    /// Returns this budget, limited to `work` units of work.
+    pub fn with_work(self, work: u64) -> Budget {
+        Budget { work: Some(work), ..self }
+    }
+
+    /// Returns this budget, limited to stop at `deadline`. This needs the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn with_deadline(self, deadline: Instant) -> Budget {
+        Budget { deadline: Some(deadline), ..self }
+    }
+}
+
+/// A diff computed within a `Budget`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BudgetedDiff<T> {
+    pub diff: Vec<DiffComponent<T>>,
+
+    /// Whether the budget ran out, so that parts of `diff` delete and insert more elements than
+    /// they need to. The diff is still valid: it turns `a` into `b`.
+    pub degraded: bool
+}
+
+/// Keeps track of how much of a `Budget` is left.
+pub struct Meter {
+    work_left: Option<u64>,
+    #[cfg(feature = "std")]
+    deadline: Option<Instant>,
+    #[cfg(feature = "std")]
+    until_clock: u64,
+    exhausted: bool
+}
+
+impl Meter {
+    pub fn new(budget: &Budget) -> Meter {
+        Meter {
+            work_left: budget.work,
+            #[cfg(feature = "std")]
+            deadline: budget.deadline,
+            #[cfg(feature = "std")]
+            until_clock: 0,
+            exhausted: false
+        }
+    }
+
+    /// Spends `units` of work, and returns whether the budget allowed it. Once the budget has run
+    /// out, this always returns `false`.
+    pub fn charge(&mut self, units: u64) -> bool {
+        if self.exhausted {
+            return false;
+        }
+
+        if let Some(ref mut work_left) = self.work_left {
+            if *work_left < units {
+                self.exhausted = true;
+                return false;
+            }
+
+            *work_left -= units;
+        }
+
+        #[cfg(feature = "std")]
+        if let Some(deadline) = self.deadline {
+            if self.until_clock <= units {
+                self.until_clock = CLOCK_INTERVAL;
+                if Instant::now() >= deadline {
+                    self.exhausted = true;
+                    return false;
+                }
+            } else {
+                self.until_clock -= units;
+            }
+        }
+
+        true
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+}
@@ -0,0 +1,184 @@
+#[cfg(feature = "std")]
+use std::error::Error;
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use DiffComponent;
+
+/// The reason two diffs could not be composed: they don't agree on the sequence `b` between them.
+///
+/// `index` is a position in `b`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComposeError {
+    /// The first diff has a different element at `index` of `b` than the second diff.
+    Mismatch { index: usize },
+
+    /// The diffs give `b` different lengths: `first` elements in the first diff, and `second` in
+    /// the second.
+    LengthMismatch { first: usize, second: usize }
+}
+
+impl fmt::Display for ComposeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ComposeError::Mismatch { index } => {
+                write!(f, "diffs disagree on element {} of the middle sequence", index)
+            },
+            ComposeError::LengthMismatch { first, second } => {
+                write!(f, "first diff produces {} elements, but second diff expects {}",
+                       first, second)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for ComposeError {}
+
+/// Composes a diff from `a` to `b` with a diff from `b` to `c` into a diff from `a` to `c`,
+/// without needing `b` itself. The elements of `b` that both diffs hold (the inserted and
+/// unchanged ones in `first`, and the unchanged and deleted ones in `second`) are checked to be
+/// equal.
+///
+/// The result turns `a` into `c`, but is not always as short as diffing `a` and `c` directly
+/// would give: elements that `first` deleted and `second` inserted back are not matched up.
+///
+/// Diffs of indices can be composed too, since the indices into `b` of both diffs are equal when
+/// they agree on `b`.
+///
+/// ```
+/// let a: Vec<_> = "abcd".chars().collect();
+/// let b: Vec<_> = "abXd".chars().collect();
+/// let c: Vec<_> = "aXdY".chars().collect();
+///
+/// let diff = patience_diff::compose(&patience_diff::patience_diff(&a, &b),
+///                                   &patience_diff::patience_diff(&b, &c)).unwrap();
+/// assert_eq!(patience_diff::apply(&a, &diff), Ok(c.clone()));
+///
+/// let other: Vec<_> = "abYd".chars().collect();
+/// assert_eq!(patience_diff::compose(&patience_diff::patience_diff(&a, &b),
+///                                   &patience_diff::patience_diff(&other, &c)),
+///            Err(patience_diff::ComposeError::Mismatch { index: 2 }));
+/// ```
+pub fn compose<T>(first: &[DiffComponent<T>], second: &[DiffComponent<T>])
+        -> Result<Vec<DiffComponent<T>>, ComposeError> where T: Clone + PartialEq {
+    let mut ret = Vec::with_capacity(first.len() + second.len());
+    let (mut i, mut j) = (0, 0);
+    let mut index = 0;
+
+    loop {
+        if let Some(DiffComponent::Deletion(elem_a)) = first.get(i) {
+            ret.push(DiffComponent::Deletion(elem_a.clone()));
+            i += 1;
+            continue;
+        }
+
+        if let Some(DiffComponent::Insertion(elem_c)) = second.get(j) {
+            ret.push(DiffComponent::Insertion(elem_c.clone()));
+            j += 1;
+            continue;
+        }
+
+        // Both diffs are either done, or at an element of `b`.
+        let (component_first, component_second) = match (first.get(i), second.get(j)) {
+            (Some(component_first), Some(component_second)) => {
+                (component_first, component_second)
+            },
+            (None, None) => return Ok(ret),
+            _ => {
+                return Err(ComposeError::LengthMismatch {
+                    first: index + middle_len(&first[i..], true),
+                    second: index + middle_len(&second[j..], false)
+                });
+            }
+        };
+
+        let (elem_a, elem_b) = match *component_first {
+            DiffComponent::Insertion(ref elem_b) => (None, elem_b),
+            DiffComponent::Unchanged(ref elem_a, ref elem_b) => (Some(elem_a), elem_b),
+            DiffComponent::Deletion(_) => unreachable!()
+        };
+
+        let (other_b, elem_c) = match *component_second {
+            DiffComponent::Unchanged(ref elem_b, ref elem_c) => (elem_b, Some(elem_c)),
+            DiffComponent::Deletion(ref elem_b) => (elem_b, None),
+            DiffComponent::Insertion(_) => unreachable!()
+        };
+
+        if elem_b != other_b {
+            return Err(ComposeError::Mismatch { index });
+        }
+
+        match (elem_a, elem_c) {
+            (Some(elem_a), Some(elem_c)) => {
+                ret.push(DiffComponent::Unchanged(elem_a.clone(), elem_c.clone()));
+            },
+            (Some(elem_a), None) => ret.push(DiffComponent::Deletion(elem_a.clone())),
+            (None, Some(elem_c)) => ret.push(DiffComponent::Insertion(elem_c.clone())),
+            (None, None) => {}
+        }
+
+        i += 1;
+        j += 1;
+        index += 1;
+    }
+}
+
+/// The number of elements of the middle sequence that `diff` holds: on its new side if it is the
+/// first diff, and on its old side otherwise.
+fn middle_len<T>(diff: &[DiffComponent<T>], is_first: bool) -> usize {
+    diff.iter().filter(|component| {
+        match **component {
+            DiffComponent::Insertion(_) => is_first,
+            DiffComponent::Unchanged(..) => true,
+            DiffComponent::Deletion(_) => !is_first
+        }
+    }).count()
+}
+
+#[test]
+fn test_compose() {
+    // The second diff inserts back the element the first one deletes. That element is still
+    // deleted and inserted by the composed diff, since the middle sequence doesn't have it.
+    let a = vec![1, 2, 3];
+    let b = vec![1, 3];
+    let c = vec![1, 2, 3, 4];
+
+    let diff = compose(&::patience_diff_indices(&a, &b), &::patience_diff_indices(&b, &c));
+    assert_eq!(diff, Ok(vec![
+        DiffComponent::Unchanged(0, 0),
+        DiffComponent::Deletion(1),
+        DiffComponent::Insertion(1),
+        DiffComponent::Unchanged(2, 2),
+        DiffComponent::Insertion(3)
+    ]));
+
+    let diff = compose(&::patience_diff(&a, &b), &::patience_diff(&b, &c)).unwrap();
+    assert_eq!(::apply(&a, &diff), Ok(c.clone()));
+
+    // Composing a diff with its inverse deletes and reinserts everything it changed.
+    let ab = ::patience_diff(&a, &b);
+    let round_trip = compose(&ab, &::invert(&ab)).unwrap();
+    assert_eq!(round_trip, vec![
+        DiffComponent::Unchanged(&1, &1),
+        DiffComponent::Deletion(&2),
+        DiffComponent::Insertion(&2),
+        DiffComponent::Unchanged(&3, &3)
+    ]);
+}
+
+#[test]
+fn test_compose_errors() {
+    let a = vec![1, 2, 3];
+    let b = vec![1, 3, 4];
+    let c = vec![3, 4, 5];
+
+    let ab = ::patience_diff(&a, &b);
+    assert_eq!(compose(&ab, &::patience_diff(&[1, 3, 4, 6], &c)),
+               Err(ComposeError::LengthMismatch { first: 3, second: 4 }));
+    assert_eq!(compose(&ab, &::patience_diff(&[1, 3], &c)),
+               Err(ComposeError::LengthMismatch { first: 3, second: 2 }));
+    assert_eq!(compose(&ab, &::patience_diff(&[1, 2, 4], &c)),
+               Err(ComposeError::Mismatch { index: 1 }));
+}
@@ -0,0 +1,172 @@
+use std::fmt;
+use std::io::{self, Write};
+
+use DiffComponent;
+use hunk::{Hunk, hunks};
+use lines::diff_lines;
+use unified::write_line;
+
+/// Writes hunks in context diff format, as `diff -c` does: a `***`/`---` header naming the two
+/// sides, followed by every hunk as its lines in `a` and then its lines in `b`.
+///
+/// Lines are written as-is, like `write_unified`, so they should keep their line terminators.
+pub fn write_context<W, L>(out: &mut W, old_name: &str, new_name: &str, hunks: &[Hunk<L>])
+        -> io::Result<()> where W: Write, L: AsRef<[u8]> {
+    writeln!(out, "*** {}", old_name)?;
+    writeln!(out, "--- {}", new_name)?;
+
+    for hunk in hunks {
+        write_context_hunk(out, hunk)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a single hunk in context diff format, starting with its `***************` separator.
+///
+/// Deleted lines are marked with `-` and inserted lines with `+`, except in runs of changes that
+/// both delete and insert lines, where they are all marked with `!`. A side without any changes
+/// is left out, leaving only its `*** a,b ****` or `--- c,d ----` header.
+pub fn write_context_hunk<W, L>(out: &mut W, hunk: &Hunk<L>) -> io::Result<()>
+        where W: Write, L: AsRef<[u8]> {
+    // Whether each component is in a run of changes that both deletes and inserts.
+    let mut replaced = vec![false; hunk.components.len()];
+    let mut start = 0;
+
+    while start < hunk.components.len() {
+        let len = hunk.components[start..].iter().take_while(|component| {
+            !matches!(**component, DiffComponent::Unchanged(..))
+        }).count();
+
+        let run = &hunk.components[start..start + len];
+        let deletes = run.iter().any(|component| matches!(*component, DiffComponent::Deletion(_)));
+        let inserts = run.iter().any(|component| matches!(*component, DiffComponent::Insertion(_)));
+        for is_replaced in &mut replaced[start..start + len] {
+            *is_replaced = deletes && inserts;
+        }
+
+        start += len.max(1);
+    }
+
+    let changed = |k: usize, mark: &'static [u8]| if replaced[k] { &b"! "[..] } else { mark };
+
+    writeln!(out, "***************")?;
+    writeln!(out, "*** {} ****", ContextRange(hunk.old_start, hunk.old_len))?;
+
+    if hunk.components.iter().any(|component| matches!(*component, DiffComponent::Deletion(_))) {
+        for (k, component) in hunk.components.iter().enumerate() {
+            match *component {
+                DiffComponent::Insertion(_) => {},
+                DiffComponent::Unchanged(ref line, _) => write_line(out, b"  ", line.as_ref())?,
+                DiffComponent::Deletion(ref line) => {
+                    write_line(out, changed(k, b"- "), line.as_ref())?;
+                }
+            }
+        }
+    }
+
+    writeln!(out, "--- {} ----", ContextRange(hunk.new_start, hunk.new_len))?;
+
+    if hunk.components.iter().any(|component| matches!(*component, DiffComponent::Insertion(_))) {
+        for (k, component) in hunk.components.iter().enumerate() {
+            match *component {
+                DiffComponent::Insertion(ref line) => {
+                    write_line(out, changed(k, b"+ "), line.as_ref())?;
+                },
+                DiffComponent::Unchanged(_, ref line) => write_line(out, b"  ", line.as_ref())?,
+                DiffComponent::Deletion(_) => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Computes the patience diff between the lines of `old` and `new` and renders it as a context
+/// diff with `context` lines of context around each change, like `unified_diff`. Returns an
+/// empty string if the two texts are identical.
+///
+/// ```
+/// let old = "a\nb\nc\n";
+/// let new = "a\nB\nc\nd\n";
+///
+/// let diff = patience_diff::context_diff(old, new, "old.txt", "new.txt", 3);
+/// assert_eq!(diff, "\
+/// *** old.txt
+/// --- new.txt
+/// ***************
+/// *** 1,3 ****
+///   a
+/// ! b
+///   c
+/// --- 1,4 ----
+///   a
+/// ! B
+///   c
+/// + d
+/// ");
+/// ```
+pub fn context_diff(old: &str, new: &str, old_name: &str, new_name: &str, context: usize)
+        -> String {
+    let diff = diff_lines(old, new);
+    let hunks = hunks(&diff, context);
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    let mut out = Vec::new();
+    write_context(&mut out, old_name, new_name, &hunks)
+        .expect("writing to a Vec cannot fail");
+
+    String::from_utf8(out).expect("diff of two strs is valid UTF-8")
+}
+
+/// Formats one side of a hunk header, as the one-based numbers of its first and last lines. A
+/// range of one line is just that line, and an empty range is reported as the line it follows.
+struct ContextRange(usize, usize);
+
+impl fmt::Display for ContextRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ContextRange(start, 0) => write!(f, "{}", start),
+            ContextRange(start, 1) => write!(f, "{}", start + 1),
+            ContextRange(start, len) => write!(f, "{},{}", start + 1, start + len)
+        }
+    }
+}
+
+#[test]
+fn test_context_diff() {
+    assert_eq!(context_diff("a\nb\n", "a\nb\n", "a", "b", 3), "");
+
+    assert_eq!(context_diff("", "x\ny\n", "/dev/null", "b/new", 3), "\
+*** /dev/null
+--- b/new
+***************
+*** 0 ****
+--- 1,2 ----
++ x
++ y
+");
+
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+    let new = "1\n3\n4\n5\n6\n7\n8\n9";
+    assert_eq!(context_diff(old, new, "a", "b", 1), "\
+*** a
+--- b
+***************
+*** 1,3 ****
+  1
+- 2
+  3
+--- 1,2 ----
+***************
+*** 8,9 ****
+  8
+! 9
+--- 7,8 ----
+  8
+! 9
+\\ No newline at end of file
+");
+}
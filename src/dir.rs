@@ -0,0 +1,329 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use DiffComponent;
+use glob::matches_path;
+use lines::{LineOptions, diff_indices_with, split_lines_bytes};
+
+/// How `diff_dirs` compares two directory trees.
+///
+/// Paths are matched against `include` and `exclude` as they are relative to the roots, with `/`
+/// between their components. A pattern without a `/` is matched against the name of each entry
+/// instead, like `diff --exclude`, so `*.o` applies in every directory. Patterns can use `*`, `?`,
+/// `[...]` classes, and `**` to match across directories, as in `src/**/*.rs`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DirOptions {
+    /// If not empty, only files and links that match one of these patterns are compared.
+    /// Directories are always searched, since files they contain may match.
+    pub include: Vec<String>,
+
+    /// Entries that match one of these patterns are skipped, along with everything in them.
+    pub exclude: Vec<String>,
+
+    /// Whether a file or link that only exists on one side is compared with an empty file, like
+    /// `diff -N`, instead of being reported as added or removed. Directories that only exist on
+    /// one side are then searched too.
+    pub new_file: bool,
+
+    /// How the lines of files are compared.
+    pub lines: LineOptions
+}
+
+/// The type of an entry in a directory tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum EntryKind {
+    File,
+    Dir,
+
+    /// A symbolic link, which is not followed. Its contents are the path it points to.
+    Symlink,
+
+    /// Anything else, such as a named pipe or a device.
+    Other
+}
+
+/// A difference between two directory trees. Paths are relative to the roots.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum DirChange {
+    /// An entry only exists in the new tree. For a directory, everything in it is new too.
+    Added { path: PathBuf, kind: EntryKind },
+
+    /// An entry only exists in the old tree. For a directory, everything in it was removed too.
+    Removed { path: PathBuf, kind: EntryKind },
+
+    /// An entry has a different type in each tree.
+    TypeChanged { path: PathBuf, old: EntryKind, new: EntryKind },
+
+    /// A file or link has different lines in each tree, as compared with `DirOptions::lines`.
+    Modified(FileDiff)
+}
+
+/// The diff between two versions of a file or link with different contents.
+///
+/// `old` and `new` are `None` for a side where the file doesn't exist, which only happens with
+/// `DirOptions::new_file`; it is then diffed as if it were empty. `diff` is the diff between their
+/// lines, as split by `split_lines_bytes`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FileDiff {
+    pub path: PathBuf,
+    pub kind: EntryKind,
+    pub old: Option<Vec<u8>>,
+    pub new: Option<Vec<u8>>,
+    pub diff: Vec<DiffComponent<usize>>
+}
+
+impl FileDiff {
+    /// The diff between the lines of the file, holding the lines themselves.
+    pub fn lines(&self) -> Vec<DiffComponent<&[u8]>> {
+        let old = lines_of(&self.old);
+        let new = lines_of(&self.new);
+
+        self.diff.iter().map(|c| {
+            match *c {
+                DiffComponent::Insertion(j) => DiffComponent::Insertion(new[j]),
+                DiffComponent::Unchanged(i, j) => DiffComponent::Unchanged(old[i], new[j]),
+                DiffComponent::Deletion(i) => DiffComponent::Deletion(old[i])
+            }
+        }).collect()
+    }
+}
+
+/// Compares the directory trees at `old` and `new`, like `diff -r`, and returns their differences
+/// sorted by path. Entries are paired by their path relative to the roots, and files and links
+/// whose contents differ are diffed line by line with patience diff. Links are never followed,
+/// except for the roots themselves. Files whose diff has no insertions or deletions, such as ones
+/// that only differ in ignored whitespace, are left out.
+///
+/// Returns an error if either root is missing or is not a directory.
+///
+/// ```no_run
+/// use patience_diff::{DirChange, DirOptions};
+///
+/// let options = DirOptions { exclude: vec!["target".to_string()], ..DirOptions::default() };
+/// for change in patience_diff::diff_dirs("old", "new", &options).unwrap() {
+///     match change {
+///         DirChange::Added { path, .. } => println!("Only in new: {}", path.display()),
+///         DirChange::Removed { path, .. } => println!("Only in old: {}", path.display()),
+///         DirChange::TypeChanged { path, .. } => println!("Changed type: {}", path.display()),
+///         DirChange::Modified(file) => println!("Modified: {}", file.path.display())
+///     }
+/// }
+/// ```
+pub fn diff_dirs<P, Q>(old: P, new: Q, options: &DirOptions) -> io::Result<Vec<DirChange>>
+        where P: AsRef<Path>, Q: AsRef<Path> {
+    for root in &[old.as_ref(), new.as_ref()] {
+        if !fs::metadata(root)?.is_dir() {
+            let message = format!("{} is not a directory", root.display());
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        }
+    }
+
+    let walker = Walker { old: old.as_ref(), new: new.as_ref(), options };
+    let mut ret = Vec::new();
+    walker.walk(Path::new(""), &mut ret)?;
+    Ok(ret)
+}
+
+struct Walker<'a> {
+    old: &'a Path,
+    new: &'a Path,
+    options: &'a DirOptions
+}
+
+impl<'a> Walker<'a> {
+    /// Compares the entries of the directory at `dir` in both trees, where it may only exist in
+    /// one of them.
+    fn walk(&self, dir: &Path, ret: &mut Vec<DirChange>) -> io::Result<()> {
+        let mut names = BTreeSet::new();
+        for root in &[self.old, self.new] {
+            // The roots themselves may be links to directories, so they are followed here.
+            if root.join(dir).is_dir() {
+                for entry in fs::read_dir(root.join(dir))? {
+                    names.insert(entry?.file_name());
+                }
+            }
+        }
+
+        for name in names {
+            let path = dir.join(name);
+            let slashed = slashed(&path);
+            if self.options.exclude.iter().any(|pattern| matches_path(pattern, &slashed)) {
+                continue;
+            }
+
+            let old_kind = kind(&self.old.join(&path))?;
+            let new_kind = kind(&self.new.join(&path))?;
+
+            if old_kind != Some(EntryKind::Dir) && new_kind != Some(EntryKind::Dir) &&
+                    !self.options.include.is_empty() &&
+                    !self.options.include.iter().any(|pattern| matches_path(pattern, &slashed)) {
+                continue;
+            }
+
+            match (old_kind, new_kind) {
+                (Some(EntryKind::Dir), Some(EntryKind::Dir)) => self.walk(&path, ret)?,
+                (Some(old), Some(new)) if old != new => {
+                    ret.push(DirChange::TypeChanged { path, old, new });
+                },
+                (Some(EntryKind::Other), Some(EntryKind::Other)) => {},
+                (Some(kind), None) if !self.options.new_file || kind == EntryKind::Other => {
+                    ret.push(DirChange::Removed { path, kind });
+                },
+                (None, Some(kind)) if !self.options.new_file || kind == EntryKind::Other => {
+                    ret.push(DirChange::Added { path, kind });
+                },
+                (Some(EntryKind::Dir), None) | (None, Some(EntryKind::Dir)) => {
+                    self.walk(&path, ret)?;
+                },
+                (Some(kind), _) | (None, Some(kind)) => {
+                    let old = contents(&self.old.join(&path), old_kind)?;
+                    let new = contents(&self.new.join(&path), new_kind)?;
+                    if old == new {
+                        continue;
+                    }
+
+                    let diff = diff_indices_with(&lines_of(&old), &lines_of(&new),
+                                                 &self.options.lines);
+                    if diff.iter().all(|c| matches!(*c, DiffComponent::Unchanged(..))) {
+                        continue;
+                    }
+
+                    ret.push(DirChange::Modified(FileDiff { path, kind, old, new, diff }));
+                },
+                (None, None) => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The type of the entry at `path`, or `None` if there is none.
+fn kind(path: &Path) -> io::Result<Option<EntryKind>> {
+    let file_type = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata.file_type(),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err)
+    };
+
+    Ok(Some(if file_type.is_file() {
+        EntryKind::File
+    } else if file_type.is_dir() {
+        EntryKind::Dir
+    } else if file_type.is_symlink() {
+        EntryKind::Symlink
+    } else {
+        EntryKind::Other
+    }))
+}
+
+/// The contents of the file or link of type `kind` at `path`, or `None` if there is none.
+fn contents(path: &Path, kind: Option<EntryKind>) -> io::Result<Option<Vec<u8>>> {
+    match kind {
+        Some(EntryKind::File) => fs::read(path).map(Some),
+        Some(EntryKind::Symlink) => {
+            let target = fs::read_link(path)?;
+            Ok(Some(target.to_string_lossy().into_owned().into_bytes()))
+        },
+        _ => Ok(None)
+    }
+}
+
+/// The lines of a file, which are none if it doesn't exist.
+fn lines_of(contents: &Option<Vec<u8>>) -> Vec<&[u8]> {
+    contents.as_ref().map_or(Vec::new(), |contents| split_lines_bytes(contents))
+}
+
+/// The relative path `path`, with `/` between its components on every platform.
+fn slashed(path: &Path) -> String {
+    let components: Vec<_> = path.iter().map(|component| component.to_string_lossy()).collect();
+    components.join("/")
+}
+
+#[test]
+fn test_diff_dirs() {
+    let root = ::std::env::temp_dir().join(format!("patience-diff-{}", ::std::process::id()));
+    let (old, new) = (root.join("old"), root.join("new"));
+
+    let files: &[(&Path, &str)] = &[
+        (&old, "same.txt"), (&new, "same.txt"),
+        (&old, "changed.txt"), (&new, "changed.txt"),
+        (&old, "removed.txt"), (&new, "added.txt"),
+        (&old, "sub/deep.rs"), (&new, "sub/deep.rs"),
+        (&old, "sub/skipped.o"), (&new, "sub/skipped.o"),
+        (&old, "kind"), (&new, "kind/inner.txt"),
+        (&new, "new_dir/file.txt")
+    ];
+
+    for &(side, file) in files {
+        let path = side.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        let contents = match (side == &*new, file) {
+            (true, "changed.txt") | (true, "sub/deep.rs") | (true, "sub/skipped.o") => "a\nB\nc\n",
+            _ => "a\nb\nc\n"
+        };
+        fs::write(path, contents).unwrap();
+    }
+
+    let options = DirOptions { exclude: vec!["*.o".to_string()], ..DirOptions::default() };
+    let changes = diff_dirs(&old, &new, &options).unwrap();
+    let summary: Vec<_> = changes.iter().map(|change| {
+        match *change {
+            DirChange::Added { ref path, .. } => format!("+ {}", slashed(path)),
+            DirChange::Removed { ref path, .. } => format!("- {}", slashed(path)),
+            DirChange::TypeChanged { ref path, .. } => format!("~ {}", slashed(path)),
+            DirChange::Modified(ref file) => format!("M {}", slashed(&file.path))
+        }
+    }).collect();
+    assert_eq!(summary, vec![
+        "+ added.txt", "M changed.txt", "~ kind", "+ new_dir", "- removed.txt", "M sub/deep.rs"
+    ]);
+
+    if let DirChange::Modified(ref file) = changes[1] {
+        assert_eq!(file.lines(), vec![
+            DiffComponent::Unchanged(&b"a\n"[..], &b"a\n"[..]),
+            DiffComponent::Insertion(&b"B\n"[..]),
+            DiffComponent::Deletion(&b"b\n"[..]),
+            DiffComponent::Unchanged(&b"c\n"[..], &b"c\n"[..])
+        ]);
+    }
+
+    let options = DirOptions {
+        include: vec!["*.txt".to_string()],
+        new_file: true,
+        ..DirOptions::default()
+    };
+    let changes = diff_dirs(&old, &new, &options).unwrap();
+    let created: Vec<_> = changes.iter().filter_map(|change| {
+        match *change {
+            DirChange::Modified(ref file) if file.old.is_none() => Some(slashed(&file.path)),
+            _ => None
+        }
+    }).collect();
+    assert_eq!(created, vec!["added.txt", "new_dir/file.txt"]);
+    assert_eq!(changes.len(), 5);
+
+    // A file that only differs in whitespace is not modified once whitespace is ignored.
+    fs::write(new.join("changed.txt"), "a\n b\t\nc\n").unwrap();
+    let mut options = DirOptions::default();
+    assert!(diff_dirs(&old, &new, &options).unwrap().iter().any(|change| {
+        matches!(*change, DirChange::Modified(ref file) if file.path == Path::new("changed.txt"))
+    }));
+    options.lines.ignore_all_space = true;
+    assert!(!diff_dirs(&old, &new, &options).unwrap().iter().any(|change| {
+        matches!(*change, DirChange::Modified(ref file) if file.path == Path::new("changed.txt"))
+    }));
+
+    let missing = diff_dirs(root.join("missing"), &new, &options).unwrap_err();
+    assert_eq!(missing.kind(), io::ErrorKind::NotFound);
+    let file = diff_dirs(&old, new.join("added.txt"), &options).unwrap_err();
+    assert_eq!(file.kind(), io::ErrorKind::InvalidInput);
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,147 @@
+//! Matching paths against shell-style glob patterns, for `DirOptions`.
+//!
+//! `*` matches any run of characters other than `/`, `?` matches any one character other than
+//! `/`, and `[...]` matches one character in a class such as `[abc]`, `[a-z]` or `[!0-9]`. `**`
+//! matches any run of characters including `/`, so `**/` matches any number of directories, even
+//! none. `\` makes the character after it match only itself.
+
+/// Whether the path `path`, relative to the roots being compared and with `/` between its
+/// components, matches `pattern`. A pattern without a `/` is matched against the last component
+/// of the path only, so that `*.o` applies in every directory. Other patterns, including ones
+/// that only start with a `/`, are matched against the whole path.
+pub fn matches_path(pattern: &str, path: &str) -> bool {
+    let is_anchored = pattern.contains('/');
+    let pattern = pattern.trim_start_matches('/');
+    let text = if is_anchored {
+        path
+    } else {
+        path.rsplit('/').next().unwrap_or(path)
+    };
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let memo = vec![None; (pattern.len() + 1) * (text.len() + 1)];
+    Matcher { pattern: &pattern, text: &text, memo }.matches(0, 0)
+}
+
+/// Matches the rest of a pattern against the rest of a text. Each star can match a run of any
+/// length, so the matcher remembers which positions it has already tried: backtracking over
+/// several stars would otherwise take exponential time.
+struct Matcher<'a> {
+    pattern: &'a [char],
+    text: &'a [char],
+    memo: Vec<Option<bool>>
+}
+
+impl<'a> Matcher<'a> {
+    /// Whether `pattern[p..]` matches `text[t..]`.
+    fn matches(&mut self, p: usize, t: usize) -> bool {
+        let key = p * (self.text.len() + 1) + t;
+        if let Some(is_match) = self.memo[key] {
+            return is_match;
+        }
+
+        let is_match = self.try_match(p, t);
+        self.memo[key] = Some(is_match);
+        is_match
+    }
+
+    fn try_match(&mut self, p: usize, t: usize) -> bool {
+        let (pattern, text) = (self.pattern, self.text);
+
+        match pattern.get(p) {
+            None => t == text.len(),
+            Some('*') if pattern.get(p + 1) == Some(&'*') => {
+                if pattern.get(p + 2) == Some(&'/') && self.matches(p + 3, t) {
+                    return true;
+                }
+
+                (t..text.len() + 1).any(|k| self.matches(p + 2, k))
+            },
+            Some('*') => {
+                let segment_len = text[t..].iter().take_while(|&&c| c != '/').count();
+                (t..t + segment_len + 1).any(|k| self.matches(p + 1, k))
+            },
+            Some('?') => text.get(t).is_some_and(|&c| c != '/') && self.matches(p + 1, t + 1),
+            Some('[') => {
+                let c = match text.get(t) {
+                    Some(&c) if c != '/' => c,
+                    _ => return false
+                };
+
+                match class(&pattern[p + 1..], c) {
+                    Some((true, rest)) => self.matches(pattern.len() - rest.len(), t + 1),
+                    Some((false, _)) => false,
+                    // A `[` without a matching `]` is an ordinary character.
+                    None => c == '[' && self.matches(p + 1, t + 1)
+                }
+            },
+            Some('\\') if p + 1 < pattern.len() => {
+                text.get(t) == Some(&pattern[p + 1]) && self.matches(p + 2, t + 1)
+            },
+            Some(&literal) => text.get(t) == Some(&literal) && self.matches(p + 1, t + 1)
+        }
+    }
+}
+
+/// Reads the character class at the start of `pattern`, which comes right after its `[`, and
+/// checks whether `c` is in it. Returns whether it is and the rest of the pattern, or `None` if
+/// the class is never closed. A `]` right at the start of the class is part of it.
+fn class(pattern: &[char], c: char) -> Option<(bool, &[char])> {
+    let (negated, mut k) = match pattern.first() {
+        Some('!') | Some('^') => (true, 1),
+        _ => (false, 0)
+    };
+
+    let start = k;
+    let mut is_in = false;
+
+    loop {
+        let first = *pattern.get(k)?;
+        if first == ']' && k != start {
+            return Some((is_in != negated, &pattern[k + 1..]));
+        }
+
+        match (pattern.get(k + 1), pattern.get(k + 2)) {
+            (Some('-'), Some(&last)) if last != ']' => {
+                is_in |= first <= c && c <= last;
+                k += 3;
+            },
+            _ => {
+                is_in |= first == c;
+                k += 1;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_matches_path() {
+    assert!(matches_path("*.o", "src/main.o"));
+    assert!(!matches_path("*.o", "src/main.c"));
+    assert!(matches_path("src/*.c", "src/main.c"));
+    assert!(!matches_path("src/*.c", "src/sub/main.c"));
+    assert!(!matches_path("/main.c", "src/main.c"));
+    assert!(matches_path("/src/main.c", "src/main.c"));
+
+    assert!(matches_path("src/**/*.c", "src/main.c"));
+    assert!(matches_path("src/**/*.c", "src/a/b/main.c"));
+    assert!(matches_path("**/target", "target"));
+    assert!(matches_path("target/**", "target/debug/build"));
+
+    assert!(matches_path("file?.txt", "file1.txt"));
+    assert!(!matches_path("file?.txt", "file10.txt"));
+    assert!(matches_path("file[0-9].txt", "file7.txt"));
+    assert!(!matches_path("file[!0-9].txt", "file7.txt"));
+    assert!(matches_path("[]x]", "]"));
+    assert!(matches_path("[a-]", "-"));
+    assert!(matches_path("a[b", "a[b"));
+    assert!(matches_path("\\*", "*"));
+    assert!(!matches_path("\\*", "x"));
+
+    // Without remembering failed positions, each star would retry every split of the rest.
+    let path = "a".repeat(200);
+    assert!(!matches_path("*a*a*a*a*a*b", &path));
+    assert!(!matches_path("**a**a**a**a**a**b", &format!("{}/{}", path, path)));
+    assert!(matches_path("*a*a*a*a*a*", &path));
+}
@@ -0,0 +1,111 @@
+use alloc::vec::Vec;
+
+use budget::Meter;
+
+/// Elements that appear more often than this in `a` are never used as anchors.
+const MAX_CHAIN_LENGTH: usize = 64;
+
+/// Finds the anchors of a histogram diff between the interned elements `a` and `b`, as pairs of
+/// positions in `a` and `b`. The anchors are a single run of matching elements: among all runs of
+/// matching elements, the one whose rarest element appears the fewest times in `a`, and the
+/// longest of those.
+///
+/// `occurrences` must have an empty entry for every ID, and is left that way. Returns `None` if
+/// `meter` runs out first.
+pub fn anchors(a: &[usize], b: &[usize], occurrences: &mut [Vec<usize>], meter: &mut Meter)
+        -> Option<Vec<(usize, usize)>> {
+    for (i, &id) in a.iter().enumerate() {
+        occurrences[id].push(i);
+    }
+
+    let ret = best_region(a, b, occurrences, meter);
+
+    for &id in a {
+        occurrences[id].clear();
+    }
+
+    ret
+}
+
+fn best_region(a: &[usize], b: &[usize], occurrences: &[Vec<usize>], meter: &mut Meter)
+        -> Option<Vec<(usize, usize)>> {
+    let count = |id: usize| occurrences[id].len();
+
+    let mut best = None;
+    let mut best_len = 0;
+    let mut lowest_count = MAX_CHAIN_LENGTH + 1;
+
+    let mut j = 0;
+    while j < b.len() {
+        let mut next_j = j + 1;
+
+        let positions = &occurrences[b[j]];
+        if positions.is_empty() || positions.len() > lowest_count {
+            j = next_j;
+            continue;
+        }
+
+        for &i in positions {
+            let (mut start_a, mut start_b) = (i, j);
+            let (mut end_a, mut end_b) = (i + 1, j + 1);
+            let mut region_count = positions.len();
+
+            while start_a > 0 && start_b > 0 && a[start_a - 1] == b[start_b - 1] {
+                start_a -= 1;
+                start_b -= 1;
+                region_count = region_count.min(count(a[start_a]));
+            }
+
+            while end_a < a.len() && end_b < b.len() && a[end_a] == b[end_b] {
+                region_count = region_count.min(count(a[end_a]));
+                end_a += 1;
+                end_b += 1;
+            }
+
+            // Every element of `b` can be compared with up to `MAX_CHAIN_LENGTH` runs, so their
+            // lengths add up to much more than the lengths of the ranges.
+            if !meter.charge((end_a - start_a) as u64) {
+                return None;
+            }
+
+            next_j = next_j.max(end_b);
+
+            if end_a - start_a > best_len || region_count < lowest_count {
+                best = Some((start_a, start_b));
+                best_len = end_a - start_a;
+                lowest_count = region_count;
+            }
+        }
+
+        j = next_j;
+    }
+
+    Some(match best {
+        Some((start_a, start_b)) => (0..best_len).map(|k| (start_a + k, start_b + k)).collect(),
+        None => vec![]
+    })
+}
+
+#[test]
+fn test_anchors() {
+    let mut occurrences = vec![Vec::new(); 4];
+    let mut meter = Meter::new(&::Budget::default());
+
+    // With `}` as 0, `x` as 1, `y` as 2 and `z` as 3.
+    let a = vec![0, 1, 0, 2, 0];
+    let b = vec![0, 2, 0, 3, 0];
+    let anchors_ab = anchors(&a, &b, &mut occurrences, &mut meter);
+    assert_eq!(anchors_ab, Some(vec![(2, 0), (3, 1), (4, 2)]));
+    assert!(occurrences.iter().all(Vec::is_empty));
+
+    let a = vec![1; 100];
+    let b = vec![1; 2];
+    assert_eq!(anchors(&a, &b, &mut occurrences, &mut meter), Some(vec![]));
+
+    // Extending runs of matching elements is charged to the budget.
+    let mut meter = Meter::new(&::Budget::default().with_work(3));
+    let a = vec![0, 1, 2, 3, 0];
+    let b = vec![0, 1, 2, 3];
+    assert_eq!(anchors(&a, &b, &mut occurrences, &mut meter), None);
+    assert!(occurrences.iter().all(Vec::is_empty));
+}
@@ -0,0 +1,159 @@
+use alloc::vec::Vec;
+use core::cmp;
+
+use DiffComponent;
+
+/// A run of nearby changes, together with the unchanged elements surrounding them.
+///
+/// `old_start` and `new_start` are zero-based positions of the hunk's first element in `a` and
+/// `b`; `old_len` and `new_len` count how many elements of `a` and `b` the hunk covers. When a
+/// side is empty (for instance, a hunk that only inserts), its start is the position the changes
+/// happen at, which is also the number of elements that come before the hunk on that side.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Hunk<T> {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub components: Vec<DiffComponent<T>>
+}
+
+/// Groups a diff into hunks the way `diff -U` does. Each change is surrounded by up to `context`
+/// unchanged elements on both sides, and hunks whose context would overlap or touch are merged
+/// into one. A diff without any insertions or deletions has no hunks.
+///
+/// ```
+/// use patience_diff::DiffComponent;
+///
+/// let a: Vec<_> = "abcdefgh".chars().collect();
+/// let b: Vec<_> = "abXdefgY".chars().collect();
+///
+/// let diff = patience_diff::patience_diff(&a, &b);
+/// let hunks = patience_diff::hunks(&diff, 1);
+///
+/// assert_eq!(hunks.len(), 2);
+/// assert_eq!((hunks[0].old_start, hunks[0].old_len), (1, 3));
+/// assert_eq!((hunks[0].new_start, hunks[0].new_len), (1, 3));
+/// assert_eq!(hunks[0].components, vec![
+///     DiffComponent::Unchanged(&'b', &'b'),
+///     DiffComponent::Insertion(&'X'),
+///     DiffComponent::Deletion(&'c'),
+///     DiffComponent::Unchanged(&'d', &'d')
+/// ]);
+/// assert_eq!((hunks[1].old_start, hunks[1].old_len), (6, 2));
+/// assert_eq!((hunks[1].new_start, hunks[1].new_len), (6, 2));
+/// ```
+pub fn hunks<T>(diff: &[DiffComponent<T>], context: usize) -> Vec<Hunk<T>> where T: Clone {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+    for (k, component) in diff.iter().enumerate() {
+        if let DiffComponent::Unchanged(..) = *component {
+            continue;
+        }
+
+        let start = k.saturating_sub(context);
+        let end = cmp::min(k + context + 1, diff.len());
+
+        if let Some(last) = ranges.last_mut() {
+            if start <= last.1 {
+                last.1 = end;
+                continue;
+            }
+        }
+
+        ranges.push((start, end));
+    }
+
+    let mut ret = Vec::with_capacity(ranges.len());
+    let mut old_pos = 0;
+    let mut new_pos = 0;
+    let mut k = 0;
+
+    for (start, end) in ranges {
+        for component in &diff[k..start] {
+            let (old, new) = component_len(component);
+            old_pos += old;
+            new_pos += new;
+        }
+
+        let mut hunk = Hunk {
+            old_start: old_pos,
+            old_len: 0,
+            new_start: new_pos,
+            new_len: 0,
+            components: diff[start..end].to_vec()
+        };
+
+        for component in &hunk.components {
+            let (old, new) = component_len(component);
+            hunk.old_len += old;
+            hunk.new_len += new;
+        }
+
+        old_pos += hunk.old_len;
+        new_pos += hunk.new_len;
+        k = end;
+
+        ret.push(hunk);
+    }
+
+    ret
+}
+
+/// How many elements of `a` and of `b` a component accounts for.
+fn component_len<T>(component: &DiffComponent<T>) -> (usize, usize) {
+    match *component {
+        DiffComponent::Insertion(_) => (0, 1),
+        DiffComponent::Unchanged(_, _) => (1, 1),
+        DiffComponent::Deletion(_) => (1, 0)
+    }
+}
+
+#[test]
+fn test_hunks() {
+    let a = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+    let b = vec![1, 2, 30, 4, 5, 6, 7, 8, 9, 10, 11];
+    let diff = ::patience_diff_indices(&a, &b);
+
+    let split = hunks(&diff, 3);
+    assert_eq!(split.len(), 2);
+    assert_eq!((split[0].old_start, split[0].old_len, split[0].new_start, split[0].new_len),
+               (0, 6, 0, 6));
+    assert_eq!((split[1].old_start, split[1].old_len, split[1].new_start, split[1].new_len),
+               (7, 3, 7, 4));
+
+    let merged = hunks(&diff, 4);
+    assert_eq!(merged.len(), 1);
+    assert_eq!((merged[0].old_start, merged[0].old_len, merged[0].new_start, merged[0].new_len),
+               (0, 10, 0, 11));
+    assert_eq!(merged[0].components, diff);
+
+    assert!(hunks(&::patience_diff_indices(&a, &a), 3).is_empty());
+}
+
+#[test]
+fn test_hunks_empty_sides() {
+    let a: Vec<u8> = vec![];
+    let b = vec![1, 2];
+
+    let insert_only = hunks(&::patience_diff_indices(&a, &b), 3);
+    assert_eq!(insert_only, vec![Hunk {
+        old_start: 0,
+        old_len: 0,
+        new_start: 0,
+        new_len: 2,
+        components: vec![DiffComponent::Insertion(0), DiffComponent::Insertion(1)]
+    }]);
+
+    let b = vec![1, 2, 3, 4, 5, 6];
+    let c = vec![1, 2, 3, 4, 5, 6, 7];
+    let append = hunks(&::patience_diff_indices(&b, &c), 0);
+    assert_eq!(append, vec![Hunk {
+        old_start: 6,
+        old_len: 0,
+        new_start: 6,
+        new_len: 1,
+        components: vec![DiffComponent::Insertion(6)]
+    }]);
+}
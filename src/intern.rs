@@ -0,0 +1,176 @@
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use alloc::vec::Vec;
+use core::hash::Hash;
+#[cfg(any(not(feature = "std"), test))]
+use core::hash::Hasher;
+
+use Sequence;
+
+/// Replaces every element of `a` and `b` with an ID, such that equal elements get the same ID.
+/// The IDs are dense: they go from zero to the returned number of distinct elements.
+#[cfg(feature = "std")]
+pub fn intern<A, B>(a: A, b: B) -> (Vec<usize>, Vec<usize>, usize)
+        where A: Sequence, B: Sequence<Item = A::Item>, A::Item: Eq + Hash {
+    let mut ids = HashMap::new();
+    let mut id = |elem| {
+        let next_id = ids.len();
+        *ids.entry(elem).or_insert(next_id)
+    };
+
+    let ids_a = (0..a.len()).map(|i| id(a.item(i))).collect();
+    let ids_b = (0..b.len()).map(|j| id(b.item(j))).collect();
+
+    (ids_a, ids_b, ids.len())
+}
+
+/// Replaces every element of `a` and `b` with an ID, such that equal elements get the same ID.
+/// The IDs are dense: they go from zero to the returned number of distinct elements.
+///
+/// Without `std`, there is no randomly seeded hasher, so inputs crafted to collide in `IdTable`'s
+/// hash can make this quadratic.
+#[cfg(not(feature = "std"))]
+pub fn intern<A, B>(a: A, b: B) -> (Vec<usize>, Vec<usize>, usize)
+        where A: Sequence, B: Sequence<Item = A::Item>, A::Item: Eq + Hash {
+    let mut ids = IdTable::new();
+
+    let ids_a = (0..a.len()).map(|i| ids.id(a.item(i))).collect();
+    let ids_b = (0..b.len()).map(|j| ids.id(b.item(j))).collect();
+
+    (ids_a, ids_b, ids.len())
+}
+
+/// A hash table from elements to IDs, for when `std`'s `HashMap` isn't available. It uses open
+/// addressing with linear probing, and is kept at most half full.
+#[cfg(any(not(feature = "std"), test))]
+struct IdTable<K> {
+    /// The element with each ID, and its hash.
+    keys: Vec<(K, u64)>,
+
+    /// One more than the ID of the element in each slot, or zero for empty slots. The number of
+    /// slots is a power of two.
+    slots: Vec<usize>
+}
+
+#[cfg(any(not(feature = "std"), test))]
+impl<K> IdTable<K> where K: Eq + Hash {
+    fn new() -> IdTable<K> {
+        IdTable { keys: Vec::new(), slots: Vec::new() }
+    }
+
+    fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns the ID of `key`, giving it the next ID if it doesn't have one yet.
+    fn id(&mut self, key: K) -> usize {
+        if 2 * (self.keys.len() + 1) > self.slots.len() {
+            self.grow();
+        }
+
+        let hash = hash(&key);
+        let mask = self.slots.len() - 1;
+        let mut slot = first_slot(hash, mask);
+
+        loop {
+            match self.slots[slot] {
+                0 => {
+                    self.keys.push((key, hash));
+                    self.slots[slot] = self.keys.len();
+                    return self.keys.len() - 1;
+                },
+                n => {
+                    let (ref other, other_hash) = self.keys[n - 1];
+                    if other_hash == hash && *other == key {
+                        return n - 1;
+                    }
+                }
+            }
+
+            slot = (slot + 1) & mask;
+        }
+    }
+
+    fn grow(&mut self) {
+        let len = (2 * self.slots.len()).max(16);
+        let mask = len - 1;
+
+        self.slots = vec![0; len];
+        for (id, &(_, hash)) in self.keys.iter().enumerate() {
+            let mut slot = first_slot(hash, mask);
+            while self.slots[slot] != 0 {
+                slot = (slot + 1) & mask;
+            }
+
+            self.slots[slot] = id + 1;
+        }
+    }
+}
+
+/// The slot to look for an element with the given hash in first. Both halves of the hash are
+/// mixed in, since the low bits of `FxHasher`'s hashes only depend on the low bits of its input.
+#[cfg(any(not(feature = "std"), test))]
+fn first_slot(hash: u64, mask: usize) -> usize {
+    (hash ^ hash >> 32) as usize & mask
+}
+
+#[cfg(any(not(feature = "std"), test))]
+fn hash<K>(key: &K) -> u64 where K: Hash {
+    let mut hasher = FxHasher { hash: 0 };
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The hash function of Firefox and rustc: fast, but not resistant to deliberate collisions.
+#[cfg(any(not(feature = "std"), test))]
+struct FxHasher {
+    hash: u64
+}
+
+#[cfg(any(not(feature = "std"), test))]
+impl FxHasher {
+    fn add(&mut self, word: u64) {
+        self.hash = (self.hash.rotate_left(5) ^ word).wrapping_mul(0x51_7c_c1_b7_27_22_0a_95);
+    }
+}
+
+#[cfg(any(not(feature = "std"), test))]
+impl Hasher for FxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(8);
+        for chunk in &mut chunks {
+            let mut word = [0; 8];
+            word.copy_from_slice(chunk);
+            self.add(u64::from_le_bytes(word));
+        }
+
+        for &byte in chunks.remainder() {
+            self.add(u64::from(byte));
+        }
+    }
+
+    fn write_u64(&mut self, word: u64) {
+        self.add(word);
+    }
+
+    fn write_usize(&mut self, word: usize) {
+        self.add(word as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+#[test]
+fn test_id_table() {
+    let mut ids = IdTable::new();
+    let words: Vec<_> = (0..1000).map(|i| format!("word {}", i % 300)).collect();
+
+    for (i, word) in words.iter().enumerate() {
+        assert_eq!(ids.id(word), i % 300);
+    }
+
+    assert_eq!(ids.len(), 300);
+}
@@ -0,0 +1,113 @@
+use alloc::vec::Vec;
+
+use DiffComponent;
+use hunk::Hunk;
+
+impl<T> DiffComponent<T> {
+    /// Turns a component of a diff from `a` to `b` into the corresponding component of the diff
+    /// from `b` to `a`: insertions become deletions, deletions become insertions, and the two
+    /// sides of an unchanged element are swapped.
+    pub fn invert(self) -> DiffComponent<T> {
+        match self {
+            DiffComponent::Insertion(elem_b) => DiffComponent::Deletion(elem_b),
+            DiffComponent::Unchanged(elem_a, elem_b) => DiffComponent::Unchanged(elem_b, elem_a),
+            DiffComponent::Deletion(elem_a) => DiffComponent::Insertion(elem_a)
+        }
+    }
+}
+
+/// Turns a diff from `a` to `b` into a diff from `b` to `a`, without diffing again. This works
+/// for diffs of indices too: an index into `b` stays one, it is just on the other side now.
+///
+/// ```
+/// let a: Vec<_> = "AaaxZ".chars().collect();
+/// let b: Vec<_> = "AxaaZ".chars().collect();
+///
+/// let diff = patience_diff::patience_diff(&a, &b);
+/// let inverted = patience_diff::invert(&diff);
+/// assert_eq!(patience_diff::apply(&b, &inverted), Ok(a.clone()));
+///
+/// let indices = patience_diff::patience_diff_indices(&a, &b);
+/// let inverted = patience_diff::invert(&indices);
+/// assert_eq!(inverted[1], patience_diff::DiffComponent::Insertion(1));
+/// ```
+pub fn invert<T>(diff: &[DiffComponent<T>]) -> Vec<DiffComponent<T>> where T: Clone {
+    diff.iter().cloned().map(DiffComponent::invert).collect()
+}
+
+/// Turns hunks of a diff from `a` to `b` into the hunks of the diff from `b` to `a`, swapping the
+/// positions and lengths of both sides along with the components.
+///
+/// ```
+/// let old = "a\nb\nc\n";
+/// let new = "a\nB\nc\nd\n";
+///
+/// let hunks = patience_diff::hunks(&patience_diff::diff_lines(old, new), 0);
+/// let inverted = patience_diff::invert_hunks(&hunks);
+/// assert_eq!((inverted[1].old_start, inverted[1].old_len), (3, 1));
+/// assert_eq!((inverted[1].new_start, inverted[1].new_len), (3, 0));
+///
+/// let new_lines = patience_diff::split_lines(new);
+/// assert_eq!(patience_diff::apply_hunks(&new_lines, &inverted).unwrap().concat(), old);
+/// ```
+pub fn invert_hunks<T>(hunks: &[Hunk<T>]) -> Vec<Hunk<T>> where T: Clone {
+    hunks.iter().map(|hunk| {
+        Hunk {
+            old_start: hunk.new_start,
+            old_len: hunk.new_len,
+            new_start: hunk.old_start,
+            new_len: hunk.old_len,
+            components: invert(&hunk.components)
+        }
+    }).collect()
+}
+
+#[test]
+fn test_invert() {
+    // A diff from nothing only inserts, so its inverse only deletes.
+    let a: Vec<&str> = vec![];
+    let b = vec!["x", "y"];
+
+    let diff = ::patience_diff(&a, &b);
+    let inverted = invert(&diff);
+    assert_eq!(inverted, vec![DiffComponent::Deletion(&"x"), DiffComponent::Deletion(&"y")]);
+    assert_eq!(::apply(&b, &inverted), Ok(a.clone()));
+    assert_eq!(invert(&inverted), diff);
+
+    // Positions swap sides along with the elements.
+    let indices = ::patience_diff_indices(&["a", "b"], &["b"]);
+    assert_eq!(invert(&indices), vec![DiffComponent::Insertion(0), DiffComponent::Unchanged(0, 1)]);
+}
+
+#[test]
+fn test_invert_hunks() {
+    let a: Vec<_> = (0..20).collect();
+    let mut b = a.clone();
+    b.remove(3);
+    b.insert(10, 100);
+    b.extend(&[101, 102]);
+
+    let hunks = ::hunks(&::patience_diff(&a, &b), 2);
+    let inverted = invert_hunks(&hunks);
+    assert_eq!(::apply_hunks(&b, &inverted), Ok(a.clone()));
+    assert_eq!(inverted, ::hunks(&invert(&::patience_diff(&a, &b)), 2));
+
+    for (hunk, inverted) in hunks.iter().zip(&inverted) {
+        assert_eq!((inverted.old_start, inverted.old_len), (hunk.new_start, hunk.new_len));
+        assert_eq!((inverted.new_start, inverted.new_len), (hunk.old_start, hunk.old_len));
+    }
+
+    // Without context, a hunk that only inserts or only deletes has an empty side, which starts
+    // where the changes happen on that side.
+    let a = vec![1, 2, 3, 4, 5, 6];
+    let b = vec![0, 1, 2, 3, 4];
+
+    let hunks = ::hunks(&::patience_diff(&a, &b), 0);
+    let inverted = invert_hunks(&hunks);
+    let ranges: Vec<_> = inverted.iter().map(|hunk| {
+        (hunk.old_start, hunk.old_len, hunk.new_start, hunk.new_len)
+    }).collect();
+    assert_eq!(ranges, vec![(0, 1, 0, 0), (5, 0, 4, 2)]);
+    assert_eq!(inverted[0].components, vec![DiffComponent::Deletion(&0)]);
+    assert_eq!(::apply_hunks(&b, &inverted), Ok(a.clone()));
+}
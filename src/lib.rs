@@ -77,33 +77,135 @@
 //! You can read Bram Cohen, "discoverer" of patience diff, describe patience diff in his own words
 //! [here][bram-blog].
 //!
+//! # Features
+//!
+//! The `std` feature is enabled by default. It adds the functions that write diffs and merges to
+//! an `std::io::Write`, `diff_dirs` to compare directory trees, deadlines for `Budget`s, and
+//! implementations of `std::error::Error`. It also builds the `patience-diff` command, which
+//! compares two files or directories and can stand in for `diff -u` or `diff -c` in scripts.
+//! Without it, this crate only needs `alloc`, so it can be used in `no_std` environments.
+//!
+//! The `serde` feature implements `Serialize` and `Deserialize` for diffs and the types made from
+//! them: `DiffComponent`, `Hunk`, `BudgetedDiff`, `MergeRegion`, `RefinedLine`, `DiffStats` and
+//! `Move`, as well as `Algorithm` and `Granularity`, and `DirChange`, `FileDiff` and `EntryKind`
+//! with the `std` feature. `FilePatch`, whose lines borrow from the parsed text, only implements
+//! `Serialize`. To store or send a diff without copying the elements it refers to, serialize the
+//! diff of indices from a function like `diff_indices`, which is only made of numbers:
+//! `Unchanged(2, 3)` is `{"Unchanged":[2,3]}` in JSON. The receiver can turn it back into a diff
+//! of elements if it has both sequences.
+//!
 //! [wiki-lcs]: https://en.wikipedia.org/wiki/Longest_common_subsequence_problem
 //! [bram-blog]: http://bramcohen.livejournal.com/73318.html
 
-extern crate lcs;
-
-use std::collections::hash_map::{HashMap, Entry};
-use std::hash::{Hash, Hasher};
-
-#[derive(Debug, Eq)]
-struct Indexed<T> {
-    index: usize,
-    value: T
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[macro_use]
+extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate core;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(all(feature = "serde", test))]
+extern crate serde_json;
+
+mod apply;
+mod budget;
+mod compose;
+#[cfg(feature = "std")]
+mod context;
+#[cfg(feature = "std")]
+mod dir;
+#[cfg(feature = "std")]
+mod glob;
+mod histogram;
+mod hunk;
+mod intern;
+mod invert;
+mod lines;
+mod merge;
+mod moves;
+mod myers;
+mod patch;
+mod refine;
+mod sequence;
+mod slider;
+mod stats;
+#[cfg(feature = "std")]
+mod unified;
+
+pub use apply::{ApplyError, apply, apply_hunks, unapply};
+pub use budget::{Budget, BudgetedDiff};
+pub use compose::{ComposeError, compose};
+#[cfg(feature = "std")]
+pub use context::{context_diff, write_context, write_context_hunk};
+#[cfg(feature = "std")]
+pub use dir::{DirChange, DirOptions, EntryKind, FileDiff, diff_dirs};
+pub use hunk::{Hunk, hunks};
+pub use invert::{invert, invert_hunks};
+pub use lines::{LineOptions, diff_lines, diff_lines_bytes, diff_lines_bytes_with, diff_lines_with,
+                split_lines, split_lines_bytes};
+pub use merge::{ConflictStyle, MergeLabels, MergeRegion, merge, merge_lines};
+#[cfg(feature = "std")]
+pub use merge::write_merge;
+pub use moves::{Move, MoveOptions, find_moves};
+pub use patch::{FilePatch, ParseError, ParseErrorKind, parse_patch};
+pub use refine::{Granularity, RefinedLine, refine};
+pub use sequence::Sequence;
+pub use slider::{compact, compact_lines};
+pub use stats::{DiffStats, diff_stats, stats};
+#[cfg(feature = "std")]
+pub use unified::{unified_diff, write_hunk, write_unified};
+
+use alloc::vec::Vec;
+use core::hash::{Hash, Hasher};
+use core::ops::Range;
+
+use budget::Meter;
+use intern::intern;
+
+/// An element compared and hashed with user-provided functions, for `patience_diff_by`.
+struct By<'a, T: 'a, E: 'a> {
+    value: &'a T,
+    hash: u64,
+    eq: &'a E
 }
 
-impl<T> PartialEq for Indexed<T> where T: PartialEq {
-    fn eq(&self, other: &Indexed<T>) -> bool {
-        self.value == other.value
+impl<'a, T, E> PartialEq for By<'a, T, E> where E: Fn(&T, &T) -> bool {
+    fn eq(&self, other: &By<'a, T, E>) -> bool {
+        self.hash == other.hash && (self.eq)(self.value, other.value)
     }
 }
 
-impl<T> Hash for Indexed<T> where T: Hash {
+impl<'a, T, E> Eq for By<'a, T, E> where E: Fn(&T, &T) -> bool {}
+
+impl<'a, T, E> Hash for By<'a, T, E> {
     fn hash<H>(&self, state: &mut H) where H: Hasher {
-        self.value.hash(state);
+        state.write_u64(self.hash);
     }
 }
 
+/// The algorithm used to find the elements that a diff keeps.
+///
+/// Both algorithms trim off the common prefix and suffix of the two sequences, pick some
+/// matching elements as anchors, and then diff the ranges between anchors the same way. When no
+/// anchor can be found, they fall back to Myers' O(ND) diff algorithm, which finds a shortest
+/// edit script in linear space.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Algorithm {
+    /// Anchors on the longest common subsequence of the elements that appear exactly once in
+    /// each sequence.
+    Patience,
+
+    /// Anchors on the longest run of matching elements that appear the fewest times in the first
+    /// sequence, as in git's `--histogram`. Unlike patience diff, this finds anchors even when no
+    /// element is unique, such as in a range made of repeated braces and blank lines.
+    Histogram
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum DiffComponent<T> {
     Insertion(T),
     Unchanged(T, T),
@@ -132,121 +234,471 @@ pub enum DiffComponent<T> {
 /// ```
 pub fn patience_diff<'a, T>(a: &'a [T], b: &'a [T]) -> Vec<DiffComponent<&'a T>>
         where T: Eq + Hash {
-    if a.len() == 0 && b.len() == 0 {
-        return vec![];
-    }
+    diff(a, b, Algorithm::Patience)
+}
 
-    if a.len() == 0 {
-        return b.iter().map(DiffComponent::Insertion).collect();
-    }
+/// Computes the patience diff between `a` and `b`, like `patience_diff`, but the `DiffComponent`s
+/// hold positions instead of references: `Insertion(j)` inserts `b[j]`, `Deletion(i)` deletes
+/// `a[i]`, and `Unchanged(i, j)` keeps `a[i]` as `b[j]`.
+///
+/// ```
+/// use patience_diff::DiffComponent;
+///
+/// let a: Vec<_> = "AaaxZ".chars().collect();
+/// let b: Vec<_> = "AxaaZ".chars().collect();
+///
+/// let diff = patience_diff::patience_diff_indices(&a, &b);
+/// assert_eq!(diff, vec![
+///     DiffComponent::Unchanged(0, 0),
+///     DiffComponent::Deletion(1),
+///     DiffComponent::Deletion(2),
+///     DiffComponent::Unchanged(3, 1),
+///     DiffComponent::Insertion(2),
+///     DiffComponent::Insertion(3),
+///     DiffComponent::Unchanged(4, 4)
+/// ]);
+/// ```
+pub fn patience_diff_indices<T>(a: &[T], b: &[T]) -> Vec<DiffComponent<usize>>
+        where T: Eq + Hash {
+    diff_indices(a, b, Algorithm::Patience)
+}
 
-    if b.len() == 0 {
-        return a.iter().map(DiffComponent::Deletion).collect();
-    }
+/// Computes the diff between `a` and `b` using the given algorithm. The `DiffComponent`s hold
+/// references to the elements in `a` and `b` they correspond to.
+///
+/// ```
+/// use patience_diff::{Algorithm, DiffComponent};
+///
+/// let a = vec!["{", "a", "}", "{", "b", "}"];
+/// let b = vec!["{", "b", "}", "{", "c", "}"];
+///
+/// let diff = patience_diff::diff(&a, &b, Algorithm::Histogram);
+/// assert_eq!(diff, vec![
+///     DiffComponent::Unchanged(&"{", &"{"),
+///     DiffComponent::Insertion(&"b"),
+///     DiffComponent::Deletion(&"a"),
+///     DiffComponent::Unchanged(&"}", &"}"),
+///     DiffComponent::Unchanged(&"{", &"{"),
+///     DiffComponent::Insertion(&"c"),
+///     DiffComponent::Deletion(&"b"),
+///     DiffComponent::Unchanged(&"}", &"}")
+/// ]);
+/// ```
+pub fn diff<'a, T>(a: &'a [T], b: &'a [T], algorithm: Algorithm) -> Vec<DiffComponent<&'a T>>
+        where T: Eq + Hash {
+    resolve(a, b, diff_indices(a, b, algorithm))
+}
 
-    let mut common_prefix = common_prefix(a.iter(), b.iter());
-    if !common_prefix.is_empty() {
-        let rest_a = &a[common_prefix.len()..];
-        let rest_b = &b[common_prefix.len()..];
-        common_prefix.extend(patience_diff(rest_a, rest_b));
-        return common_prefix;
-    }
+/// Computes the diff between `a` and `b` using the given algorithm. The `DiffComponent`s hold
+/// positions in `a` and `b`, like those returned by `patience_diff_indices`.
+pub fn diff_indices<T>(a: &[T], b: &[T], algorithm: Algorithm) -> Vec<DiffComponent<usize>>
+        where T: Eq + Hash {
+    diff_indices_within(a, b, algorithm, &Budget::default()).diff
+}
 
-    let common_suffix = common_suffix(a.iter(), b.iter());
-    if !common_suffix.is_empty() {
-        let prev_a = &a[..a.len() - common_suffix.len()];
-        let prev_b = &b[..b.len() - common_suffix.len()];
-        let mut prev_diff = patience_diff(prev_a, prev_b);
-        prev_diff.extend(common_suffix);
+/// Computes the diff between `a` and `b` using the given algorithm, like `diff`, but stops
+/// looking for a good diff once `budget` runs out. The ranges that are left are then replaced as
+/// a whole, so the diff is still valid but coarser, and is marked as `degraded`.
+///
+/// ```
+/// use patience_diff::{Algorithm, Budget};
+///
+/// let a: Vec<_> = (0..1000).map(|i| i % 7).collect();
+/// let b: Vec<_> = (0..1000).map(|i| i % 11).collect();
+///
+/// let budget = Budget::default().with_work(10_000);
+/// let limited = patience_diff::diff_within(&a, &b, Algorithm::Patience, &budget);
+///
+/// assert!(limited.degraded);
+/// assert_eq!(patience_diff::apply(&a, &limited.diff), Ok(b.clone()));
+/// ```
+pub fn diff_within<'a, T>(a: &'a [T], b: &'a [T], algorithm: Algorithm, budget: &Budget)
+        -> BudgetedDiff<&'a T> where T: Eq + Hash {
+    let limited = diff_indices_within(a, b, algorithm, budget);
+    BudgetedDiff { diff: resolve(a, b, limited.diff), degraded: limited.degraded }
+}
 
-        return prev_diff;
-    }
+/// Computes the diff between `a` and `b` using the given algorithm within `budget`, like
+/// `diff_within`. The `DiffComponent`s hold positions in `a` and `b`, like those returned by
+/// `patience_diff_indices`.
+pub fn diff_indices_within<T>(a: &[T], b: &[T], algorithm: Algorithm, budget: &Budget)
+        -> BudgetedDiff<usize> where T: Eq + Hash {
+    diff_sequences_within(a, b, algorithm, budget)
+}
 
-    let indexed_a: Vec<_> = a.iter()
-        .enumerate()
-        .map(|(i, val)| Indexed { index: i, value: val })
-        .collect();
-    let indexed_b: Vec<_> = b.iter()
-        .enumerate()
-        .map(|(i, val)| Indexed { index: i, value: val })
-        .collect();
-
-    let uniq_a = unique_elements(&indexed_a);
-    let uniq_b = unique_elements(&indexed_b);
-
-    let table = lcs::LcsTable::new(&uniq_a, &uniq_b);
-    let lcs = table.longest_common_subsequence();
-
-    if lcs.is_empty() {
-        let table = lcs::LcsTable::new(&indexed_a, &indexed_b);
-        return table.diff().into_iter().map(|c| {
-            match c {
-                lcs::DiffComponent::Insertion(elem_b) => {
-                    DiffComponent::Insertion(&b[elem_b.index])
+/// Computes the diff between two sequences that are not necessarily slices, such as `VecDeque`s
+/// or ropes, using the given algorithm. The `DiffComponent`s hold positions in `a` and `b`, like
+/// those returned by `patience_diff_indices`.
+///
+/// ```
+/// use std::collections::VecDeque;
+///
+/// use patience_diff::{Algorithm, DiffComponent};
+///
+/// let mut a: VecDeque<_> = vec![2, 3].into_iter().collect();
+/// a.push_front(1);
+/// let b = vec![1, 3];
+///
+/// let diff = patience_diff::diff_sequences(&a, &b, Algorithm::Patience);
+/// assert_eq!(diff, vec![
+///     DiffComponent::Unchanged(0, 0),
+///     DiffComponent::Deletion(1),
+///     DiffComponent::Unchanged(2, 1)
+/// ]);
+/// ```
+pub fn diff_sequences<A, B>(a: A, b: B, algorithm: Algorithm) -> Vec<DiffComponent<usize>>
+        where A: Sequence, B: Sequence<Item = A::Item>, A::Item: Eq + Hash {
+    diff_sequences_within(a, b, algorithm, &Budget::default()).diff
+}
+
+/// Computes the diff between two sequences that are not necessarily slices using the given
+/// algorithm within `budget`, like `diff_within`.
+pub fn diff_sequences_within<A, B>(a: A, b: B, algorithm: Algorithm, budget: &Budget)
+        -> BudgetedDiff<usize> where A: Sequence, B: Sequence<Item = A::Item>, A::Item: Eq + Hash {
+    // Every element is hashed and compared with other elements just once, here. From then on,
+    // the diff only compares integers.
+    let (ids_a, ids_b, id_count) = intern(a, b);
+    let (diff, degraded) = diff_interned(&ids_a, &ids_b, id_count, algorithm, budget, Vec::new());
+    BudgetedDiff { diff, degraded }
+}
+
+/// Diffs two sequences of interned elements, and adds the components of the diff to `ret` in
+/// order. Returns `ret`, and whether the budget ran out.
+fn diff_interned<R>(ids_a: &[usize], ids_b: &[usize], id_count: usize, algorithm: Algorithm,
+                    budget: &Budget, ret: R) -> (R, bool) where R: Extend<DiffComponent<usize>> {
+    let mut differ = Differ {
+        a: ids_a,
+        b: ids_b,
+        algorithm,
+        meter: Meter::new(budget),
+        myers: myers::Myers::new(),
+        counts: vec![0; id_count],
+        occurrences: match algorithm {
+            Algorithm::Patience => Vec::new(),
+            Algorithm::Histogram => vec![Vec::new(); id_count]
+        },
+        stack: vec![Task::Diff(0..ids_a.len(), 0..ids_b.len())],
+        ret
+    };
+
+    differ.run();
+    (differ.ret, differ.meter.is_exhausted())
+}
+
+/// Computes the patience diff between `a` and `b`, comparing elements by the key `key` extracts
+/// from them rather than by the elements themselves. The `DiffComponent`s still hold references
+/// to the original elements.
+///
+/// ```
+/// use patience_diff::DiffComponent;
+///
+/// let a = vec![(1, "apple"), (2, "banana"), (3, "cherry")];
+/// let b = vec![(1, "APPLE"), (3, "cherry"), (4, "date")];
+///
+/// let diff = patience_diff::patience_diff_by_key(&a, &b, |&(id, _)| id);
+/// assert_eq!(diff, vec![
+///     DiffComponent::Unchanged(&(1, "apple"), &(1, "APPLE")),
+///     DiffComponent::Deletion(&(2, "banana")),
+///     DiffComponent::Unchanged(&(3, "cherry"), &(3, "cherry")),
+///     DiffComponent::Insertion(&(4, "date"))
+/// ]);
+/// ```
+pub fn patience_diff_by_key<'a, T, K, F>(a: &'a [T], b: &'a [T], mut key: F)
+        -> Vec<DiffComponent<&'a T>> where K: Eq + Hash, F: FnMut(&T) -> K {
+    let keys_a: Vec<_> = a.iter().map(&mut key).collect();
+    let keys_b: Vec<_> = b.iter().map(&mut key).collect();
+
+    resolve(a, b, diff_indices(&keys_a, &keys_b, Algorithm::Patience))
+}
+
+/// Computes the patience diff between `a` and `b`, comparing elements with `eq` rather than with
+/// `==`. `hash` must be consistent with `eq`: elements that `eq` considers equal must have the
+/// same hash. The `DiffComponent`s still hold references to the original elements.
+///
+/// ```
+/// use std::collections::hash_map::DefaultHasher;
+/// use std::hash::{Hash, Hasher};
+///
+/// use patience_diff::DiffComponent;
+///
+/// let a = vec!["Foo", "bar", "baz"];
+/// let b = vec!["foo", "BAZ", "qux"];
+///
+/// let diff = patience_diff::patience_diff_by(&a, &b, |s| {
+///     let mut hasher = DefaultHasher::new();
+///     s.to_lowercase().hash(&mut hasher);
+///     hasher.finish()
+/// }, |x, y| x.eq_ignore_ascii_case(y));
+///
+/// assert_eq!(diff, vec![
+///     DiffComponent::Unchanged(&"Foo", &"foo"),
+///     DiffComponent::Deletion(&"bar"),
+///     DiffComponent::Unchanged(&"baz", &"BAZ"),
+///     DiffComponent::Insertion(&"qux")
+/// ]);
+/// ```
+pub fn patience_diff_by<'a, T, H, E>(a: &'a [T], b: &'a [T], mut hash: H, eq: E)
+        -> Vec<DiffComponent<&'a T>> where H: FnMut(&T) -> u64, E: Fn(&T, &T) -> bool {
+    let by_a: Vec<_> = a.iter().map(|value| By { value, hash: hash(value), eq: &eq }).collect();
+    let by_b: Vec<_> = b.iter().map(|value| By { value, hash: hash(value), eq: &eq }).collect();
+
+    resolve(a, b, diff_indices(&by_a, &by_b, Algorithm::Patience))
+}
+
+/// Turns a diff holding positions in `a` and `b` into one holding references to the elements at
+/// those positions.
+fn resolve<'a, T>(a: &'a [T], b: &'a [T], diff: Vec<DiffComponent<usize>>)
+        -> Vec<DiffComponent<&'a T>> {
+    diff.into_iter().map(|c| {
+        match c {
+            DiffComponent::Insertion(j) => DiffComponent::Insertion(&b[j]),
+            DiffComponent::Unchanged(i, j) => DiffComponent::Unchanged(&a[i], &b[j]),
+            DiffComponent::Deletion(i) => DiffComponent::Deletion(&a[i])
+        }
+    }).collect()
+}
+
+/// A step of computing a diff. Rather than recursing into the ranges it splits the sequences
+/// into, `diff_indices` pushes the work that is left onto a stack of tasks, so that deeply nested
+/// inputs cannot overflow the call stack. Tasks are pushed in reverse, so that they are popped,
+/// and push their components onto the diff, in order.
+enum Task {
+    /// Diffs `a[range_a]` and `b[range_b]` with the chosen algorithm.
+    Diff(Range<usize>, Range<usize>),
+
+    /// Diffs `a[range_a]` and `b[range_b]` with Myers' algorithm.
+    Myers(Range<usize>, Range<usize>),
+
+    /// Keeps `len` elements, starting at `a[start_a]` and `b[start_b]`.
+    Unchanged(usize, usize, usize)
+}
+
+/// The state of computing a diff between two sequences of interned elements. The diff is added to
+/// `ret` as it is found.
+struct Differ<'a, R> {
+    a: &'a [usize],
+    b: &'a [usize],
+    algorithm: Algorithm,
+    meter: Meter,
+    myers: myers::Myers,
+
+    /// How many times every ID appears in the range being counted by `unique_elements`. This is
+    /// all zeroes in between.
+    counts: Vec<usize>,
+
+    /// Where every ID appears in the range `histogram::anchors` is looking at. This is all empty
+    /// in between, and only used by histogram diffs.
+    occurrences: Vec<Vec<usize>>,
+
+    stack: Vec<Task>,
+    ret: R
+}
+
+impl<'a, R> Differ<'a, R> where R: Extend<DiffComponent<usize>> {
+    fn run(&mut self) {
+        while let Some(task) = self.stack.pop() {
+            match task {
+                Task::Diff(range_a, range_b) => {
+                    if self.meter.charge((range_a.len() + range_b.len()) as u64) {
+                        self.diff_range(range_a, range_b);
+                    } else {
+                        push_replacement(range_a, range_b, &mut self.ret);
+                    }
                 },
-                lcs::DiffComponent::Unchanged(elem_a, elem_b) => {
-                    DiffComponent::Unchanged(&a[elem_a.index], &b[elem_b.index])
+                Task::Myers(range_a, range_b) => {
+                    if self.meter.charge((range_a.len() + range_b.len()) as u64) {
+                        self.myers.conquer(self.a, self.b, range_a, range_b, &mut self.meter,
+                                           &mut self.stack, &mut self.ret);
+                    } else {
+                        push_replacement(range_a, range_b, &mut self.ret);
+                    }
                 },
-                lcs::DiffComponent::Deletion(elem_a) => {
-                    DiffComponent::Deletion(&a[elem_a.index])
+                Task::Unchanged(start_a, start_b, len) => {
+                    push_unchanged(start_a, start_b, len, &mut self.ret);
                 }
             }
-        }).collect();
+        }
     }
 
-    let mut ret = Vec::new();
-    let mut last_index_a = 0;
-    let mut last_index_b = 0;
+    /// Diffs `a[range_a]` and `b[range_b]`. Components that can be found right away are pushed
+    /// onto the diff, and the ranges that are left are pushed onto the stack.
+    fn diff_range(&mut self, range_a: Range<usize>, range_b: Range<usize>) {
+        let sub_a = &self.a[range_a.clone()];
+        let sub_b = &self.b[range_b.clone()];
+
+        if sub_a.is_empty() {
+            self.ret.extend(range_b.map(DiffComponent::Insertion));
+            return;
+        }
+
+        if sub_b.is_empty() {
+            self.ret.extend(range_a.map(DiffComponent::Deletion));
+            return;
+        }
+
+        let prefix_len = common_prefix_len(sub_a, sub_b);
+        if prefix_len != 0 {
+            push_unchanged(range_a.start, range_b.start, prefix_len, &mut self.ret);
 
-    for (match_a, match_b) in lcs {
-        let subset_a = &a[last_index_a..match_a.index];
-        let subset_b = &b[last_index_b..match_b.index];
+            let rest_a = range_a.start + prefix_len..range_a.end;
+            let rest_b = range_b.start + prefix_len..range_b.end;
+            self.stack.push(Task::Diff(rest_a, rest_b));
+            return;
+        }
 
-        ret.extend(patience_diff(subset_a, subset_b));
+        let suffix_len = common_suffix_len(sub_a, sub_b);
+        if suffix_len != 0 {
+            let prev_a = range_a.start..range_a.end - suffix_len;
+            let prev_b = range_b.start..range_b.end - suffix_len;
+            self.stack.push(Task::Unchanged(prev_a.end, prev_b.end, suffix_len));
+            self.stack.push(Task::Diff(prev_a, prev_b));
+            return;
+        }
 
-        ret.push(DiffComponent::Unchanged(match_a.value, match_b.value));
+        let anchors = match self.algorithm {
+            Algorithm::Patience => self.patience_anchors(sub_a, sub_b),
+            Algorithm::Histogram => {
+                histogram::anchors(sub_a, sub_b, &mut self.occurrences, &mut self.meter)
+            }
+        };
 
-        last_index_a = match_a.index + 1;
-        last_index_b = match_b.index + 1;
+        let anchors = match anchors {
+            Some(anchors) => anchors,
+            None => {
+                push_replacement(range_a, range_b, &mut self.ret);
+                return;
+            }
+        };
+
+        if anchors.is_empty() {
+            self.stack.push(Task::Myers(range_a, range_b));
+            return;
+        }
+
+        let mut next_index_a = range_a.end;
+        let mut next_index_b = range_b.end;
+
+        for &(match_a, match_b) in anchors.iter().rev() {
+            let match_a = range_a.start + match_a;
+            let match_b = range_b.start + match_b;
+
+            self.stack.push(Task::Diff(match_a + 1..next_index_a, match_b + 1..next_index_b));
+            self.stack.push(Task::Unchanged(match_a, match_b, 1));
+
+            next_index_a = match_a;
+            next_index_b = match_b;
+        }
+
+        self.stack.push(Task::Diff(range_a.start..next_index_a, range_b.start..next_index_b));
     }
 
-    let subset_a = &a[last_index_a..a.len()];
-    let subset_b = &b[last_index_b..b.len()];
-    ret.extend(patience_diff(subset_a, subset_b));
+    /// Finds the longest common subsequence between the elements that are unique in `a` and the
+    /// elements that are unique in `b`, as pairs of positions in `a` and `b`. Returns `None` if
+    /// the budget runs out first.
+    fn patience_anchors(&mut self, a: &[usize], b: &[usize]) -> Option<Vec<(usize, usize)>> {
+        let uniq_a = unique_elements(a, &mut self.counts);
+        let uniq_b = unique_elements(b, &mut self.counts);
+
+        // Every element that is unique in `b` is marked with its position in `b`, plus one, so
+        // that the elements of `a` that are unique in `b` too can find their match.
+        for &j in &uniq_b {
+            self.counts[b[j]] = j + 1;
+        }
 
-    ret
+        let matches: Vec<_> = uniq_a.iter().filter_map(|&i| {
+            match self.counts[a[i]] {
+                0 => None,
+                j => Some((i, j - 1))
+            }
+        }).collect();
+
+        for &j in &uniq_b {
+            self.counts[b[j]] = 0;
+        }
+
+        longest_increasing_subsequence(&matches, &mut self.meter)
+    }
 }
 
-fn common_prefix<'a, T, I>(a: I, b: I) -> Vec<DiffComponent<I::Item>>
-        where I: Iterator<Item = &'a T>, T: Eq {
-    a.zip(b)
-        .take_while(|&(elem_a, elem_b)| elem_a == elem_b)
-        .map(|(elem_a, elem_b)| DiffComponent::Unchanged(elem_a, elem_b))
-        .collect()
+/// Pushes `len` unchanged pairs, starting at `a[start_a]` and `b[start_b]`.
+fn push_unchanged<R>(start_a: usize, start_b: usize, len: usize, ret: &mut R)
+        where R: Extend<DiffComponent<usize>> {
+    ret.extend((0..len).map(|k| DiffComponent::Unchanged(start_a + k, start_b + k)));
 }
 
-fn common_suffix<'a, T, I>(a: I, b: I) -> Vec<DiffComponent<I::Item>>
-        where I: DoubleEndedIterator<Item = &'a T>, T: Eq {
-    common_prefix(a.rev(), b.rev())
+/// Pushes the deletion of `a[range_a]` followed by the insertion of `b[range_b]`.
+fn push_replacement<R>(range_a: Range<usize>, range_b: Range<usize>, ret: &mut R)
+        where R: Extend<DiffComponent<usize>> {
+    ret.extend(range_a.map(DiffComponent::Deletion));
+    ret.extend(range_b.map(DiffComponent::Insertion));
 }
 
-fn unique_elements<'a, T: Eq + Hash>(elems: &'a [T]) -> Vec<&'a T> {
-    let mut counts: HashMap<&T, usize> = HashMap::new();
+/// Finds a longest subsequence of `matches`, which are pairs of positions in `a` and `b` sorted by
+/// their position in `a`, whose positions in `b` are increasing too. Since every element is in at
+/// most one match, this is a longest common subsequence of the matched elements.
+///
+/// This uses patience sorting, in O(n log n) time: every match is put on the leftmost pile whose
+/// top is further in `b`, so that the matches on a pile are the ones that end the longest
+/// subsequences of the same length. Of all the longest subsequences, the one taken is the one
+/// whose last match is earliest in `a`, then whose match before that is, and so on.
+///
+/// Returns `None` if `meter` runs out first.
+fn longest_increasing_subsequence(matches: &[(usize, usize)], meter: &mut Meter)
+        -> Option<Vec<(usize, usize)>> {
+    // The matches on every pile, in the order they were put there, so that their positions in
+    // `b` are decreasing.
+    let mut piles: Vec<Vec<(usize, usize)>> = Vec::new();
+
+    for &(i, j) in matches {
+        if !meter.charge(1) {
+            return None;
+        }
 
-    for elem in elems {
-        match counts.entry(elem) {
-            Entry::Occupied(mut e) => {
-                *e.get_mut() = e.get() + 1;
-            },
-            Entry::Vacant(e) => {
-                e.insert(1);
-            }
+        let pile = piles.partition_point(|pile| pile[pile.len() - 1].1 < j);
+        if pile == piles.len() {
+            piles.push(vec![(i, j)]);
+        } else {
+            piles[pile].push((i, j));
         }
     }
 
-    elems.iter()
-        .filter(|elem| counts.get(elem) == Some(&1))
-        .collect()
+    let mut ret = Vec::with_capacity(piles.len());
+    let mut next_j = usize::MAX;
+
+    // Every pile has a match before the one taken from the next pile, and the first of them is
+    // the earliest in `a`.
+    for pile in piles.iter().rev() {
+        let (i, j) = pile[pile.partition_point(|&(_, j)| j > next_j)];
+        ret.push((i, j));
+        next_j = j;
+    }
+
+    ret.reverse();
+    Some(ret)
+}
+
+fn common_prefix_len<T>(a: &[T], b: &[T]) -> usize where T: Eq {
+    a.iter().zip(b).take_while(|&(elem_a, elem_b)| elem_a == elem_b).count()
+}
+
+fn common_suffix_len<T>(a: &[T], b: &[T]) -> usize where T: Eq {
+    a.iter().rev().zip(b.iter().rev()).take_while(|&(elem_a, elem_b)| elem_a == elem_b).count()
+}
+
+/// Finds the positions of the elements that appear exactly once in `ids`. `counts` must have an
+/// entry for every ID, and be all zeroes; it is left that way.
+fn unique_elements(ids: &[usize], counts: &mut [usize]) -> Vec<usize> {
+    for &id in ids {
+        counts[id] += 1;
+    }
+
+    let ret = (0..ids.len()).filter(|&i| counts[ids[i]] == 1).collect();
+
+    for &id in ids {
+        counts[id] = 0;
+    }
+
+    ret
 }
 
 #[test]
@@ -279,7 +731,198 @@ fn test_patience_diff() {
     ]);
 }
 
+#[test]
+fn test_patience_diff_indices() {
+    let a = vec![1, 10, 11, 4];
+    let b = vec![1, 10, 11, 2, 3, 10, 11, 4];
+
+    let diff = patience_diff_indices(&a, &b);
+    assert_eq!(diff, vec![
+        DiffComponent::Unchanged(0, 0),
+        DiffComponent::Unchanged(1, 1),
+        DiffComponent::Unchanged(2, 2),
+        DiffComponent::Insertion(3),
+        DiffComponent::Insertion(4),
+        DiffComponent::Insertion(5),
+        DiffComponent::Insertion(6),
+        DiffComponent::Unchanged(3, 7)
+    ]);
+
+    let a: Vec<_> = "xyz".chars().collect();
+    assert_eq!(patience_diff_indices(&a, &[]), vec![
+        DiffComponent::Deletion(0),
+        DiffComponent::Deletion(1),
+        DiffComponent::Deletion(2),
+    ]);
+    assert_eq!(patience_diff_indices(&[], &a), vec![
+        DiffComponent::Insertion(0),
+        DiffComponent::Insertion(1),
+        DiffComponent::Insertion(2),
+    ]);
+}
+
+#[test]
+fn test_diff_algorithms_round_trip() {
+    // A small linear congruential generator, so that the inputs are varied but reproducible.
+    let mut seed: u32 = 1;
+    let mut next = || {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        (seed >> 16) % 4
+    };
+
+    for _ in 0..200 {
+        let len_a = next() as usize * 3;
+        let len_b = next() as usize * 3;
+        let a: Vec<_> = (0..len_a).map(|_| next()).collect();
+        let b: Vec<_> = (0..len_b).map(|_| next()).collect();
+
+        for &algorithm in &[Algorithm::Patience, Algorithm::Histogram] {
+            let diff = diff(&a, &b, algorithm);
+            assert_eq!(apply(&a, &diff), Ok(b.clone()));
+            assert_eq!(unapply(&b, &diff), Ok(a.clone()));
+        }
+
+        let compacted = compact(&a, &b, &diff_indices(&a, &b, Algorithm::Patience));
+        assert_eq!(apply(&a, &resolve(&a, &b, compacted)), Ok(b.clone()));
+    }
+}
+
+#[test]
+fn test_diff_within() {
+    let a: Vec<_> = (0..500).map(|i| i * 7 % 13).collect();
+    let b: Vec<_> = (0..500).map(|i| i * 5 % 13).collect();
+
+    let unlimited = diff_indices_within(&a, &b, Algorithm::Patience, &Budget::default());
+    assert!(!unlimited.degraded);
+    assert_eq!(unlimited.diff, patience_diff_indices(&a, &b));
+
+    let nothing = Budget::default().with_work(0);
+    let replaced = diff_indices_within(&a, &b, Algorithm::Patience, &nothing);
+    assert!(replaced.degraded);
+    assert_eq!(replaced.diff.len(), a.len() + b.len());
+
+    for &work in &[10, 100, 1000, 10_000, 100_000] {
+        let budget = Budget::default().with_work(work);
+        for &algorithm in &[Algorithm::Patience, Algorithm::Histogram] {
+            let limited = diff_within(&a, &b, algorithm, &budget);
+            assert_eq!(apply(&a, &limited.diff), Ok(b.clone()));
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_diff_within_deadline() {
+    use std::time::{Duration, Instant};
+
+    let a: Vec<_> = (0..500).map(|i| i * 7 % 13).collect();
+    let b: Vec<_> = (0..500).map(|i| i * 5 % 13).collect();
+
+    let deadline = Instant::now() - Duration::from_secs(1);
+    let past = Budget::default().with_deadline(deadline);
+    assert!(diff_within(&a, &b, Algorithm::Histogram, &past).degraded);
+}
+
+#[test]
+fn test_diff_deep_nesting() {
+    // Each level of nesting is made of two unique anchors around the next level, and of copies
+    // of the previous level's anchors, so that those are only unique once the level around them
+    // has been split off. Diffing this in a thread with a small stack would overflow it if every
+    // level of nesting took a level of recursion.
+    let mut a = vec![];
+    let mut b = vec![];
+    for level in 0..500 {
+        let (u, v, z) = (3 * level, 3 * level + 1, 3 * level + 2);
+        let copies = if level == 0 { vec![] } else { vec![u - 3, v - 3] };
+
+        a = [&copies[..], &[u], &a[..], &[v]].concat();
+        b = [&copies.iter().rev().cloned().collect::<Vec<_>>()[..], &[u], &b[..], &[v, z]].concat();
+    }
+
+    let diff = ::std::thread::Builder::new()
+        .stack_size(32 * 1024)
+        .spawn(move || {
+            let diff = patience_diff_indices(&a, &b);
+            (a, b, diff)
+        })
+        .unwrap()
+        .join();
+
+    let (a, b, diff) = diff.expect("diff overflowed the stack");
+    assert_eq!(apply(&a, &resolve(&a, &b, diff)), Ok(b.clone()));
+}
+
+#[test]
+fn test_diff_many_unique_reordered() {
+    // Every element is unique, so anchors are picked among all of them. That must not take time
+    // or memory proportional to the product of the lengths.
+    let a: Vec<_> = (0..200_000).collect();
+    let b: Vec<_> = a.iter().rev().cloned().collect();
+
+    let diff = patience_diff_indices(&a, &b);
+    assert_eq!(diff.iter().filter(|c| matches!(**c, DiffComponent::Unchanged(..))).count(), 1);
+    assert_eq!(diff[0], DiffComponent::Insertion(0));
+}
+
+#[test]
+fn test_patience_diff_by() {
+    let a = vec!["fn main() {", "    foo();", "}"];
+    let b = vec!["fn main() {", "\tfoo();", "\tbar();", "}"];
+
+    let normalized = |line: &&str| line.trim().to_string();
+    assert_eq!(patience_diff_by_key(&a, &b, normalized), vec![
+        DiffComponent::Unchanged(&"fn main() {", &"fn main() {"),
+        DiffComponent::Unchanged(&"    foo();", &"\tfoo();"),
+        DiffComponent::Insertion(&"\tbar();"),
+        DiffComponent::Unchanged(&"}", &"}")
+    ]);
+
+    // A hash that is the same for everything is consistent with any equality, if slow.
+    let by_len = patience_diff_by(&a, &b, |_| 0, |x, y| x.len() == y.len());
+    assert_eq!(apply(&a, &by_len).map(|applied| applied.len()), Ok(b.len()));
+    assert_eq!(by_len[0], DiffComponent::Unchanged(&"fn main() {", &"fn main() {"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde() {
+    let a = vec!["a\n", "b\n", "c\n"];
+    let b = vec!["a\n", "c\n", "d\n"];
+
+    let diff = diff_indices(&a, &b, Algorithm::Patience);
+    let json = serde_json::to_string(&diff).unwrap();
+    assert_eq!(json, r#"[{"Unchanged":[0,0]},{"Deletion":1},{"Unchanged":[2,1]},{"Insertion":2}]"#);
+    assert_eq!(serde_json::from_str::<Vec<DiffComponent<usize>>>(&json).unwrap(), diff);
+
+    let hunks = hunks(&diff_lines("a\nb\n", "a\nc\n"), 1);
+    let json = serde_json::to_string(&hunks).unwrap();
+    let owned: Vec<Hunk<String>> = serde_json::from_str(&json).unwrap();
+    assert_eq!(serde_json::to_string(&owned).unwrap(), json);
+
+    // The lines of a patch end in newlines, which JSON escapes, so its hunks read back as owned
+    // strings.
+    let patches = parse_patch("--- a\n+++ b\n@@ -1,3 +1,3 @@\n \"a\"\n-b\n+c\n d\n").unwrap();
+    let json: serde_json::Value = serde_json::to_value(&patches).unwrap();
+    assert_eq!(json[0]["old_name"], "a");
+    assert_eq!(json[0]["new_name"], "b");
+    let owned: Vec<Hunk<String>> = serde_json::from_value(json[0]["hunks"].clone()).unwrap();
+    assert_eq!(serde_json::to_value(&owned).unwrap(), json[0]["hunks"]);
+
+    let old: Vec<String> = split_lines("\"a\"\nb\nd\n").into_iter().map(String::from).collect();
+    assert_eq!(apply_hunks(&old, &owned).unwrap().concat(), "\"a\"\nc\nd\n");
+
+    #[cfg(feature = "std")]
+    {
+        let change = DirChange::Added { path: "src/new.rs".into(), kind: EntryKind::File };
+        let json = serde_json::to_string(&change).unwrap();
+        assert_eq!(json, r#"{"Added":{"path":"src/new.rs","kind":"File"}}"#);
+        assert_eq!(serde_json::from_str::<DirChange>(&json).unwrap(), change);
+    }
+}
+
 #[test]
 fn test_unique_elements() {
-    assert_eq!(vec![&2, &4, &5], unique_elements(&[1, 2, 3, 3, 4, 5, 1]));
+    let mut counts = vec![0; 6];
+    assert_eq!(vec![1, 4, 5], unique_elements(&[1, 2, 3, 3, 4, 5, 1], &mut counts));
+    assert_eq!(counts, vec![0; 6]);
 }
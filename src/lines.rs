@@ -0,0 +1,247 @@
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use core::hash::Hash;
+
+use {Algorithm, DiffComponent};
+use slider;
+
+/// How `diff_lines_with` diffs lines: which differences between lines it ignores when matching
+/// them up, and whether it slides blocks of changes. The default does neither, like `diff_lines`.
+///
+/// Whitespace is what C's `isspace` accepts: spaces, tabs, line terminators, vertical tabs and
+/// form feeds. Options that ignore whitespace at the end of a line also ignore whether the line
+/// has a terminator at all.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LineOptions {
+    /// Ignores whitespace entirely, like `git diff -w`.
+    pub ignore_all_space: bool,
+
+    /// Ignores changes in the amount of whitespace, and whitespace at the end of lines, like
+    /// `git diff -b`.
+    pub ignore_space_change: bool,
+
+    /// Ignores whitespace at the end of lines, like `git diff --ignore-space-at-eol`.
+    pub ignore_space_at_eol: bool,
+
+    /// Ignores a carriage return at the end of lines, like `git diff --ignore-cr-at-eol`.
+    pub ignore_cr_at_eol: bool,
+
+    /// Slides blocks of changed lines that could be placed in several positions to where they
+    /// start and end on boundaries between blocks of code, as `compact_lines` does.
+    pub indent_heuristic: bool
+}
+
+impl LineOptions {
+    /// The form of `line` that lines are compared by.
+    fn normalize<'a>(&self, line: &'a [u8]) -> Cow<'a, [u8]> {
+        if self.ignore_all_space {
+            return Cow::Owned(line.iter().cloned().filter(|&byte| !is_space(byte)).collect());
+        }
+
+        if self.ignore_space_change || self.ignore_space_at_eol {
+            let end = line.iter().rposition(|&byte| !is_space(byte)).map_or(0, |i| i + 1);
+            let line = &line[..end];
+
+            if !self.ignore_space_change {
+                return Cow::Borrowed(line);
+            }
+
+            let mut ret = Vec::with_capacity(line.len());
+            for &byte in line {
+                if !is_space(byte) {
+                    ret.push(byte);
+                } else if ret.last() != Some(&b' ') {
+                    ret.push(b' ');
+                }
+            }
+
+            return Cow::Owned(ret);
+        }
+
+        if self.ignore_cr_at_eol {
+            if line.ends_with(b"\r\n") {
+                let mut ret = line[..line.len() - 2].to_vec();
+                ret.push(b'\n');
+                return Cow::Owned(ret);
+            } else if line.ends_with(b"\r") {
+                return Cow::Borrowed(&line[..line.len() - 1]);
+            }
+        }
+
+        Cow::Borrowed(line)
+    }
+}
+
+/// Splits `text` into lines. Every line keeps its terminator (`\n`, or `\r\n`), so concatenating
+/// the lines gives back `text` exactly. Only the last line can lack a terminator, and an empty
+/// `text` has no lines at all.
+///
+/// ```
+/// let lines = patience_diff::split_lines("a\r\nb\n\nc");
+/// assert_eq!(lines, vec!["a\r\n", "b\n", "\n", "c"]);
+/// ```
+pub fn split_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// Splits `text` into lines, like `split_lines`, for text that is not necessarily UTF-8.
+pub fn split_lines_bytes(text: &[u8]) -> Vec<&[u8]> {
+    text.split_inclusive(|&byte| byte == b'\n').collect()
+}
+
+/// Computes the patience diff between the lines of `old` and `new`, as split by `split_lines`.
+/// Lines keep their terminators, so a line that only differs in its ending (`\n` versus `\r\n`,
+/// or a missing final newline) is reported as changed, and the lines of the `old` (or `new`) side
+/// of the diff concatenate back to `old` (or `new`).
+///
+/// ```
+/// use patience_diff::DiffComponent;
+///
+/// let diff = patience_diff::diff_lines("a\nb\nc\n", "a\nc\nd");
+/// assert_eq!(diff, vec![
+///     DiffComponent::Unchanged("a\n", "a\n"),
+///     DiffComponent::Deletion("b\n"),
+///     DiffComponent::Unchanged("c\n", "c\n"),
+///     DiffComponent::Insertion("d")
+/// ]);
+/// ```
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffComponent<&'a str>> {
+    diff_split(&split_lines(old), &split_lines(new))
+}
+
+/// Computes the patience diff between the lines of `old` and `new`, like `diff_lines`, for text
+/// that is not necessarily UTF-8.
+pub fn diff_lines_bytes<'a>(old: &'a [u8], new: &'a [u8]) -> Vec<DiffComponent<&'a [u8]>> {
+    diff_split(&split_lines_bytes(old), &split_lines_bytes(new))
+}
+
+/// Computes the patience diff between the lines of `old` and `new`, like `diff_lines`, but lines
+/// that only differ in ways `options` ignores are matched up. The diff still holds the original
+/// lines. With `indent_heuristic`, lines are slid by their original indentation.
+///
+/// ```
+/// use patience_diff::{DiffComponent, LineOptions};
+///
+/// let options = LineOptions { ignore_space_change: true, ..LineOptions::default() };
+/// let diff = patience_diff::diff_lines_with("if x {\n  y();\n}\n", "if x  {\n\ty();\n}\n",
+///                                           &options);
+/// assert_eq!(diff, vec![
+///     DiffComponent::Unchanged("if x {\n", "if x  {\n"),
+///     DiffComponent::Unchanged("  y();\n", "\ty();\n"),
+///     DiffComponent::Unchanged("}\n", "}\n")
+/// ]);
+/// ```
+pub fn diff_lines_with<'a>(old: &'a str, new: &'a str, options: &LineOptions)
+        -> Vec<DiffComponent<&'a str>> {
+    diff_split_with(&split_lines(old), &split_lines(new), options)
+}
+
+/// Computes the patience diff between the lines of `old` and `new`, like `diff_lines_with`, for
+/// text that is not necessarily UTF-8.
+pub fn diff_lines_bytes_with<'a>(old: &'a [u8], new: &'a [u8], options: &LineOptions)
+        -> Vec<DiffComponent<&'a [u8]>> {
+    diff_split_with(&split_lines_bytes(old), &split_lines_bytes(new), options)
+}
+
+fn diff_split<'a, L>(old: &[&'a L], new: &[&'a L]) -> Vec<DiffComponent<&'a L>>
+        where L: ?Sized + Eq + Hash {
+    resolve(old, new, ::patience_diff_indices(old, new))
+}
+
+fn diff_split_with<'a, L>(old: &[&'a L], new: &[&'a L], options: &LineOptions)
+        -> Vec<DiffComponent<&'a L>> where L: ?Sized + AsRef<[u8]> {
+    resolve(old, new, diff_indices_with(old, new, options))
+}
+
+/// Computes the patience diff between the lines `old` and `new` like `diff_lines_with`, as a diff
+/// of indices.
+pub fn diff_indices_with<L>(old: &[&L], new: &[&L], options: &LineOptions)
+        -> Vec<DiffComponent<usize>> where L: ?Sized + AsRef<[u8]> {
+    let keys_old: Vec<_> = old.iter().map(|line| options.normalize(line.as_ref())).collect();
+    let keys_new: Vec<_> = new.iter().map(|line| options.normalize(line.as_ref())).collect();
+
+    let mut diff = ::diff_indices(&keys_old, &keys_new, Algorithm::Patience);
+    if options.indent_heuristic {
+        diff = slider::slide(&keys_old, &keys_new, &diff, Some(&slider::indents(old)),
+                             Some(&slider::indents(new)));
+    }
+
+    diff
+}
+
+fn resolve<'a, L>(old: &[&'a L], new: &[&'a L], diff: Vec<DiffComponent<usize>>)
+        -> Vec<DiffComponent<&'a L>> where L: ?Sized {
+    diff.into_iter().map(|c| {
+        match c {
+            DiffComponent::Insertion(j) => DiffComponent::Insertion(new[j]),
+            DiffComponent::Unchanged(i, j) => DiffComponent::Unchanged(old[i], new[j]),
+            DiffComponent::Deletion(i) => DiffComponent::Deletion(old[i])
+        }
+    }).collect()
+}
+
+fn is_space(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\x0b' | b'\x0c' | b'\r')
+}
+
+#[test]
+fn test_split_lines() {
+    assert!(split_lines("").is_empty());
+    assert!(split_lines_bytes(b"").is_empty());
+    assert_eq!(split_lines("\n"), vec!["\n"]);
+    assert_eq!(split_lines_bytes(b"a\r\n\xffb"), vec![&b"a\r\n"[..], &b"\xffb"[..]]);
+}
+
+#[test]
+fn test_diff_lines_round_trip() {
+    let old = "one\r\ntwo\r\nthree\r\nfour";
+    let new = "one\ntwo\r\nthree\r\nfour\n";
+
+    let diff = diff_lines(old, new);
+    assert_eq!(diff, vec![
+        DiffComponent::Insertion("one\n"),
+        DiffComponent::Deletion("one\r\n"),
+        DiffComponent::Unchanged("two\r\n", "two\r\n"),
+        DiffComponent::Unchanged("three\r\n", "three\r\n"),
+        DiffComponent::Insertion("four\n"),
+        DiffComponent::Deletion("four")
+    ]);
+
+    let mut rebuilt_old = String::new();
+    let mut rebuilt_new = String::new();
+    for component in diff {
+        match component {
+            DiffComponent::Insertion(line) => rebuilt_new.push_str(line),
+            DiffComponent::Unchanged(line_a, line_b) => {
+                rebuilt_old.push_str(line_a);
+                rebuilt_new.push_str(line_b);
+            },
+            DiffComponent::Deletion(line) => rebuilt_old.push_str(line)
+        }
+    }
+
+    assert_eq!(rebuilt_old, old);
+    assert_eq!(rebuilt_new, new);
+}
+
+#[test]
+fn test_diff_lines_with() {
+    let old = "a b\r\nc  d \n e\n";
+    let new = "a b\nc d\ne";
+
+    let changed = |options: LineOptions| {
+        diff_lines_with(old, new, &options).iter()
+            .filter(|c| !matches!(**c, DiffComponent::Unchanged(..)))
+            .count()
+    };
+
+    assert_eq!(changed(LineOptions::default()), 6);
+    assert_eq!(changed(LineOptions { ignore_cr_at_eol: true, ..LineOptions::default() }), 4);
+    assert_eq!(changed(LineOptions { ignore_space_at_eol: true, ..LineOptions::default() }), 4);
+    assert_eq!(changed(LineOptions { ignore_space_change: true, ..LineOptions::default() }), 2);
+    assert_eq!(changed(LineOptions { ignore_all_space: true, ..LineOptions::default() }), 0);
+
+    let options = LineOptions { ignore_all_space: true, ..LineOptions::default() };
+    assert_eq!(diff_lines_bytes_with(b"x\n", b"\tx\n", &options),
+               vec![DiffComponent::Unchanged(&b"x\n"[..], &b"\tx\n"[..])]);
+}
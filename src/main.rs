@@ -0,0 +1,504 @@
+//! `patience-diff`: compares two files, or two directory trees, line by line with patience diff.
+//!
+//! It takes the same options as GNU `diff` for what it supports, and exits with the same status:
+//! 0 if the inputs are the same, 1 if they differ, and 2 if there was trouble.
+
+extern crate patience_diff;
+
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use patience_diff::{DiffComponent, DirChange, DirOptions, EntryKind, Hunk};
+
+const USAGE: &str = "\
+Usage: patience-diff [OPTION]... OLD NEW
+Compare files, or directories recursively, line by line using patience diff.
+Either of OLD and NEW can be `-` for standard input. If one is a directory and the
+other a file, the file is compared with the file of the same name in the directory.
+
+  -u, -U NUM, --unified[=NUM]   output NUM (default 3) lines of unified context
+  -c, -C NUM, --context[=NUM]   output NUM (default 3) lines of copied context
+  -r, --recursive               compare directories recursively (always done)
+  -N, --new-file                treat files missing on one side as empty
+  -x, --exclude=PAT             skip files and directories that match PAT
+      --include=PAT             only compare files that match PAT
+  -b, --ignore-space-change     ignore changes in the amount of white space
+  -w, --ignore-all-space        ignore all white space
+  -Z, --ignore-trailing-space   ignore white space at line end
+      --strip-trailing-cr       strip trailing carriage return on input
+      --indent-heuristic        slide changes to line up with blocks of code
+      --color[=WHEN]            color output; WHEN is `never`, `always` or `auto`
+  -h, --help                    display this help and exit
+
+Exit status is 0 if inputs are the same, 1 if different, 2 if trouble.
+";
+
+const BOLD: &str = "\x1b[1m";
+const CYAN: &str = "\x1b[36m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    Unified,
+    Context
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Options {
+    format: Format,
+    context: usize,
+    color: bool,
+    dir: DirOptions,
+
+    /// The options as they were given, to repeat in the `diff` line before every pair of files
+    /// compared in directories.
+    flags: Vec<String>,
+
+    old: String,
+    new: String
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = match parse_args(&args, io::stdout().is_terminal()) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            print!("{}", USAGE);
+            process::exit(0);
+        },
+        Err(message) => {
+            eprintln!("patience-diff: {}", message);
+            eprintln!("patience-diff: Try 'patience-diff --help' for more information.");
+            process::exit(2);
+        }
+    };
+
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    let result = run(&options, &mut out).and_then(|differ| {
+        out.flush().map(|()| differ).map_err(|err| err.to_string())
+    });
+
+    let status = match result {
+        Ok(false) => 0,
+        Ok(true) => 1,
+        Err(message) => {
+            eprintln!("patience-diff: {}", message);
+            2
+        }
+    };
+
+    process::exit(status);
+}
+
+/// Parses the arguments after the program name. Returns `None` if help was asked for.
+fn parse_args(args: &[String], is_terminal: bool) -> Result<Option<Options>, String> {
+    let args = split_bundles(args);
+    let mut format = Format::Unified;
+    let mut context = 3;
+    let mut color = false;
+    let mut dir = DirOptions::default();
+    let mut flags = Vec::new();
+    let mut paths = Vec::new();
+
+    let mut k = 0;
+    while k < args.len() {
+        let arg = &args[k];
+        k += 1;
+
+        if arg == "--" {
+            paths.extend(args[k..].iter().cloned());
+            break;
+        }
+
+        if !arg.starts_with('-') || arg == "-" {
+            paths.push(arg.clone());
+            continue;
+        }
+
+        flags.push(arg.clone());
+
+        // The value of an option that takes one, either after `=` or as the next argument.
+        let (name, inline_value) = match arg.find('=') {
+            Some(eq) if arg.starts_with("--") => (&arg[..eq], Some(arg[eq + 1..].to_string())),
+            _ => (&arg[..], None)
+        };
+        let mut value = |k: &mut usize| -> Result<String, String> {
+            if let Some(ref value) = inline_value {
+                return Ok(value.clone());
+            }
+
+            let value = args.get(*k).cloned()
+                .ok_or(format!("option '{}' requires an argument", name))?;
+            flags.push(value.clone());
+            *k += 1;
+            Ok(value)
+        };
+
+        match name {
+            "-u" => format = Format::Unified,
+            "-c" => format = Format::Context,
+            "--unified" | "--context" => {
+                format = if name == "--unified" { Format::Unified } else { Format::Context };
+                if let Some(ref lines) = inline_value {
+                    context = parse_context(lines)?;
+                }
+            },
+            "-U" | "-C" => {
+                format = if name == "-U" { Format::Unified } else { Format::Context };
+                context = parse_context(&value(&mut k)?)?;
+            },
+            "-r" | "--recursive" => {},
+            "-N" | "--new-file" => dir.new_file = true,
+            "-x" | "--exclude" => dir.exclude.push(value(&mut k)?),
+            "--include" => dir.include.push(value(&mut k)?),
+            "-b" | "--ignore-space-change" => dir.lines.ignore_space_change = true,
+            "-w" | "--ignore-all-space" => dir.lines.ignore_all_space = true,
+            "-Z" | "--ignore-trailing-space" => dir.lines.ignore_space_at_eol = true,
+            "--strip-trailing-cr" => dir.lines.ignore_cr_at_eol = true,
+            "--indent-heuristic" => dir.lines.indent_heuristic = true,
+            "--color" => {
+                color = match inline_value.as_ref().map(|when| &when[..]) {
+                    None | Some("auto") => is_terminal,
+                    Some("always") => true,
+                    Some("never") => false,
+                    Some(when) => return Err(format!("invalid color '{}'", when))
+                };
+            },
+            "-h" | "--help" => return Ok(None),
+            _ if name.len() > 2 && (name.starts_with("-U") || name.starts_with("-C")) &&
+                    !name.starts_with("--") => {
+                format = if name.starts_with("-U") { Format::Unified } else { Format::Context };
+                context = parse_context(&name[2..])?;
+            },
+            _ => return Err(format!("unrecognized option '{}'", arg))
+        }
+    }
+
+    if paths.len() > 2 {
+        return Err(format!("extra operand '{}'", paths[2]));
+    }
+
+    if paths.len() < 2 {
+        let last = args.last().map_or("patience-diff", |arg| &arg[..]);
+        return Err(format!("missing operand after '{}'", last));
+    }
+
+    let new = paths.pop().unwrap();
+    let old = paths.pop().unwrap();
+    Ok(Some(Options { format, context, color, dir, flags, old, new }))
+}
+
+/// Splits options that are bundled together, like `-ruN`, into `-r`, `-u` and `-N`. Only options
+/// that don't take a value can be bundled.
+fn split_bundles(args: &[String]) -> Vec<String> {
+    let mut ret = Vec::new();
+
+    for (k, arg) in args.iter().enumerate() {
+        if arg == "--" {
+            ret.extend(args[k..].iter().cloned());
+            break;
+        }
+
+        if arg.len() > 2 && arg.starts_with('-') && !arg.starts_with("--") &&
+                arg[1..].chars().all(|flag| "ucrNbwZh".contains(flag)) {
+            ret.extend(arg[1..].chars().map(|flag| format!("-{}", flag)));
+        } else {
+            ret.push(arg.clone());
+        }
+    }
+
+    ret
+}
+
+fn parse_context(lines: &str) -> Result<usize, String> {
+    lines.parse().map_err(|_| format!("invalid context length '{}'", lines))
+}
+
+/// Compares the inputs named in `options` and writes their differences to `out`. Returns whether
+/// they differ.
+fn run<W>(options: &Options, out: &mut W) -> Result<bool, String> where W: Write {
+    let mut old = PathBuf::from(&options.old);
+    let mut new = PathBuf::from(&options.new);
+
+    match (old.is_dir(), new.is_dir()) {
+        (true, true) => return diff_dirs(options, &old, &new, out),
+        (true, false) => old = old.join(file_name(&new)?),
+        (false, true) => new = new.join(file_name(&old)?),
+        (false, false) => {}
+    }
+
+    let old_contents = read(&old, options.dir.new_file)?;
+    let new_contents = read(&new, options.dir.new_file)?;
+    if old_contents == new_contents {
+        return Ok(false);
+    }
+
+    let old_name = old.to_string_lossy();
+    let new_name = new.to_string_lossy();
+    diff_files(options, &old_name, &new_name, &old_contents, &new_contents, None, out)
+}
+
+/// Compares two directory trees, writing the differences in every pair of files after a `diff`
+/// line, and a line for every other difference, like `diff -r`.
+fn diff_dirs<W>(options: &Options, old: &Path, new: &Path, out: &mut W) -> Result<bool, String>
+        where W: Write {
+    let changes = patience_diff::diff_dirs(old, new, &options.dir)
+        .map_err(|err| format!("{} and {}: {}", old.display(), new.display(), err))?;
+    let mut differ = false;
+
+    for change in changes {
+        let written = match change {
+            DirChange::Added { path, .. } => only_in(out, new, &path),
+            DirChange::Removed { path, .. } => only_in(out, old, &path),
+            DirChange::TypeChanged { path, old: old_kind, new: new_kind } => {
+                writeln!(out, "File {} is a {} while file {} is a {}",
+                         old.join(&path).display(), kind_name(old_kind),
+                         new.join(&path).display(), kind_name(new_kind))
+            },
+            DirChange::Modified(file) => {
+                let old_name = old.join(&file.path).to_string_lossy().into_owned();
+                let new_name = new.join(&file.path).to_string_lossy().into_owned();
+                let old_contents = file.old.as_ref().map_or(&[][..], |old| &old[..]);
+                let new_contents = file.new.as_ref().map_or(&[][..], |new| &new[..]);
+
+                let header = format!("diff {}{} {}", options.flags.iter()
+                                         .map(|flag| format!("{} ", flag))
+                                         .collect::<String>(), old_name, new_name);
+                differ |= diff_files(options, &old_name, &new_name, old_contents, new_contents,
+                                     Some((&header, &file.diff)), out)?;
+                continue;
+            }
+        };
+
+        written.map_err(|err| err.to_string())?;
+        differ = true;
+    }
+
+    Ok(differ)
+}
+
+/// Writes the diff between the contents of two files, and returns whether they differ in a way
+/// the options don't ignore. `dir_diff` is the line to write before the diff when comparing
+/// directories, with the diff already computed for it.
+fn diff_files<W>(options: &Options, old_name: &str, new_name: &str, old: &[u8], new: &[u8],
+                 dir_diff: Option<(&str, &[DiffComponent<usize>])>, out: &mut W)
+        -> Result<bool, String> where W: Write {
+    let write_err = |err: io::Error| err.to_string();
+
+    if old.contains(&0) || new.contains(&0) {
+        if let Some((header, _)) = dir_diff {
+            writeln!(out, "{}", header).map_err(write_err)?;
+        }
+
+        writeln!(out, "Binary files {} and {} differ", old_name, new_name).map_err(write_err)?;
+        return Ok(true);
+    }
+
+    let old_lines = patience_diff::split_lines_bytes(old);
+    let new_lines = patience_diff::split_lines_bytes(new);
+    let diff = match dir_diff {
+        Some((_, diff)) => resolve(&old_lines, &new_lines, diff),
+        None => patience_diff::diff_lines_bytes_with(old, new, &options.dir.lines)
+    };
+
+    let hunks = patience_diff::hunks(&diff, options.context);
+    if hunks.is_empty() {
+        return Ok(false);
+    }
+
+    if let Some((header, _)) = dir_diff {
+        write_colored(out, options.color, BOLD, header.as_bytes()).map_err(write_err)?;
+    }
+
+    write_hunks(options, old_name, new_name, &hunks, out).map_err(write_err)?;
+    Ok(true)
+}
+
+/// Writes the file headers and hunks of a diff in the chosen format, coloring them if asked to.
+fn write_hunks<W>(options: &Options, old_name: &str, new_name: &str, hunks: &[Hunk<&[u8]>],
+                  out: &mut W) -> io::Result<()> where W: Write {
+    let (old_marker, new_marker) = match options.format {
+        Format::Unified => ("---", "+++"),
+        Format::Context => ("***", "---")
+    };
+
+    write_colored(out, options.color, BOLD, format!("{} {}", old_marker, old_name).as_bytes())?;
+    write_colored(out, options.color, BOLD, format!("{} {}", new_marker, new_name).as_bytes())?;
+
+    for hunk in hunks {
+        let mut written = Vec::new();
+        match options.format {
+            Format::Unified => patience_diff::write_hunk(&mut written, hunk)?,
+            Format::Context => patience_diff::write_context_hunk(&mut written, hunk)?
+        }
+
+        if !options.color {
+            out.write_all(&written)?;
+            continue;
+        }
+
+        // Every line of a hunk starts with a marker that says what it is. In context format, `!`
+        // marks deleted lines before the `--- c,d ----` header, and inserted lines after.
+        let mut in_new = false;
+        for line in patience_diff::split_lines_bytes(&written) {
+            let color = match line[0] {
+                b'@' => Some(CYAN),
+                b'*' if line.starts_with(b"*** ") => Some(CYAN),
+                b'-' if line.starts_with(b"--- ") && options.format == Format::Context => {
+                    in_new = true;
+                    Some(CYAN)
+                },
+                b'-' => Some(RED),
+                b'+' => Some(GREEN),
+                b'!' if in_new => Some(GREEN),
+                b'!' => Some(RED),
+                _ => None
+            };
+
+            match color {
+                Some(color) => {
+                    write_colored(out, true, color, line.strip_suffix(b"\n").unwrap_or(line))?;
+                },
+                None => out.write_all(line)?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes `line` and a newline, in `color` if `enabled`.
+fn write_colored<W>(out: &mut W, enabled: bool, color: &str, line: &[u8]) -> io::Result<()>
+        where W: Write {
+    if enabled {
+        out.write_all(color.as_bytes())?;
+        out.write_all(line)?;
+        out.write_all(RESET.as_bytes())?;
+    } else {
+        out.write_all(line)?;
+    }
+
+    out.write_all(b"\n")
+}
+
+fn only_in<W>(out: &mut W, root: &Path, path: &Path) -> io::Result<()> where W: Write {
+    // Joining an empty path would add a trailing `/`.
+    let dir = match path.parent() {
+        Some(parent) if parent != Path::new("") => root.join(parent),
+        _ => root.to_path_buf()
+    };
+    let name = path.file_name().unwrap_or(path.as_os_str());
+    writeln!(out, "Only in {}: {}", dir.display(), name.to_string_lossy())
+}
+
+fn kind_name(kind: EntryKind) -> &'static str {
+    match kind {
+        EntryKind::File => "regular file",
+        EntryKind::Dir => "directory",
+        EntryKind::Symlink => "symbolic link",
+        EntryKind::Other => "special file"
+    }
+}
+
+fn resolve<'a>(old: &[&'a [u8]], new: &[&'a [u8]], diff: &[DiffComponent<usize>])
+        -> Vec<DiffComponent<&'a [u8]>> {
+    diff.iter().map(|c| {
+        match *c {
+            DiffComponent::Insertion(j) => DiffComponent::Insertion(new[j]),
+            DiffComponent::Unchanged(i, j) => DiffComponent::Unchanged(old[i], new[j]),
+            DiffComponent::Deletion(i) => DiffComponent::Deletion(old[i])
+        }
+    }).collect()
+}
+
+fn file_name(path: &Path) -> Result<&Path, String> {
+    path.file_name().map(Path::new).ok_or(format!("{}: not a file name", path.display()))
+}
+
+/// Reads the file at `path`, or standard input for `-`. A missing file is empty if `new_file`.
+fn read(path: &Path, new_file: bool) -> Result<Vec<u8>, String> {
+    let result = if path == Path::new("-") {
+        let mut contents = Vec::new();
+        io::stdin().read_to_end(&mut contents).map(|_| contents)
+    } else {
+        fs::read(path)
+    };
+
+    match result {
+        Ok(contents) => Ok(contents),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound && new_file => Ok(Vec::new()),
+        Err(err) => Err(format!("{}: {}", path.display(), err))
+    }
+}
+
+#[test]
+fn test_parse_args() {
+    let args = |args: &[&str]| -> Vec<String> { args.iter().map(|arg| arg.to_string()).collect() };
+
+    let options = parse_args(&args(&["-U1", "-N", "--exclude=*.o", "a", "b"]), false).unwrap();
+    let options = options.unwrap();
+    assert_eq!((options.format, options.context), (Format::Unified, 1));
+    assert_eq!(options.dir.exclude, vec!["*.o"]);
+    assert!(options.dir.new_file);
+    assert_eq!(options.flags, args(&["-U1", "-N", "--exclude=*.o"]));
+    assert_eq!((&options.old[..], &options.new[..]), ("a", "b"));
+
+    let options = parse_args(&args(&["-C", "5", "--color", "-", "b"]), true).unwrap().unwrap();
+    assert_eq!((options.format, options.context, options.color), (Format::Context, 5, true));
+    assert_eq!(options.flags, args(&["-C", "5", "--color"]));
+    assert_eq!(options.old, "-");
+
+    assert_eq!(parse_args(&args(&["--help"]), false), Ok(None));
+    assert!(parse_args(&args(&["-U", "x", "a", "b"]), false).is_err());
+    assert!(parse_args(&args(&["--bogus", "a", "b"]), false).is_err());
+    assert!(parse_args(&args(&["a"]), false).is_err());
+    assert!(parse_args(&args(&["a", "b", "c"]), false).is_err());
+}
+
+#[test]
+fn test_run() {
+    let root = env::temp_dir().join(format!("patience-diff-bin-{}", process::id()));
+    fs::create_dir_all(root.join("old")).unwrap();
+    fs::create_dir_all(root.join("new")).unwrap();
+    fs::write(root.join("old/file"), "a\nb\nc\n").unwrap();
+    fs::write(root.join("new/file"), "a\nB\nc\n").unwrap();
+    fs::write(root.join("old/gone"), "x\n").unwrap();
+
+    let path = |name: &str| root.join(name).to_string_lossy().into_owned();
+    let diff = |old: &str, new: &str, flags: &[&str]| {
+        let mut args: Vec<String> = flags.iter().map(|flag| flag.to_string()).collect();
+        args.push(path(old));
+        args.push(path(new));
+
+        let mut out = Vec::new();
+        let differ = run(&parse_args(&args, false).unwrap().unwrap(), &mut out);
+        (differ, String::from_utf8(out).unwrap())
+    };
+
+    assert_eq!(diff("old/file", "old/file", &[]), (Ok(false), String::new()));
+    assert_eq!(diff("old/file", "new", &["-U0"]), (Ok(true), format!("\
+--- {}
++++ {}
+@@ -2 +2 @@
+-b
++B
+", path("old/file"), path("new/file"))));
+
+    let (differ, out) = diff("old", "new", &["-c"]);
+    assert_eq!(differ, Ok(true));
+    assert!(out.starts_with(&format!("diff -c {} {}\n*** ", path("old/file"), path("new/file"))));
+    assert!(out.contains("! B\n"));
+    assert!(out.ends_with(&format!("Only in {}: gone\n", path("old"))));
+
+    let (differ, _) = diff("old/missing", "new/file", &[]);
+    assert!(differ.unwrap_err().contains("missing"));
+    assert_eq!(diff("old/missing", "old/gone", &["-N"]).0, Ok(true));
+
+    fs::remove_dir_all(&root).unwrap();
+}
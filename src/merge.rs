@@ -0,0 +1,377 @@
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+
+use alloc::vec::Vec;
+use core::hash::Hash;
+use core::ops::Range;
+
+use DiffComponent;
+use lines::split_lines;
+
+/// A region of a three-way merge.
+///
+/// `Resolved` holds elements that both sides agree on: either neither side changed them, only one
+/// side did, or both made the same change. `Conflict` holds a part of `base` that `ours` and
+/// `theirs` changed in different ways, along with what each side replaced it with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum MergeRegion<T> {
+    Resolved(Vec<T>),
+    Conflict { base: Vec<T>, ours: Vec<T>, theirs: Vec<T> }
+}
+
+/// How `write_merge` writes conflicts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictStyle {
+    /// Only the two sides, between `<<<<<<<`, `=======` and `>>>>>>>` markers. Lines at the
+    /// start or end of the conflict that both sides agree on are moved out of the markers.
+    Merge,
+
+    /// Like `Merge`, but also shows the base after a `|||||||` marker, and keeps every line of
+    /// the conflict between the markers.
+    Diff3,
+
+    /// Like `Diff3`, but lines at the start or end of the conflict that both sides agree on are
+    /// moved out of the markers, like in `Merge`.
+    ZealousDiff3
+}
+
+/// The names written after the conflict markers of `write_merge`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MergeLabels<'a> {
+    pub ours: &'a str,
+    pub base: &'a str,
+    pub theirs: &'a str
+}
+
+/// Merges the changes from `base` to `ours` and from `base` to `theirs`. Both sides are aligned
+/// with `base` using patience diffs. The result alternates between conflicts and the cleanly
+/// merged regions between them, so two `Resolved` regions are never next to each other, and
+/// none of them is empty.
+///
+/// ```
+/// use patience_diff::MergeRegion;
+///
+/// let base = vec![1, 2, 3, 4, 5];
+/// let ours = vec![1, 20, 3, 4, 5];
+/// let theirs = vec![1, 2, 3, 40, 5];
+///
+/// let merged = patience_diff::merge(&base, &ours, &theirs);
+/// assert_eq!(merged, vec![MergeRegion::Resolved(vec![&1, &20, &3, &40, &5])]);
+///
+/// let theirs = vec![1, 200, 3, 4, 5];
+/// let merged = patience_diff::merge(&base, &ours, &theirs);
+/// assert_eq!(merged[1], MergeRegion::Conflict {
+///     base: vec![&2],
+///     ours: vec![&20],
+///     theirs: vec![&200]
+/// });
+/// ```
+pub fn merge<'a, T>(base: &'a [T], ours: &'a [T], theirs: &'a [T]) -> Vec<MergeRegion<&'a T>>
+        where T: Eq + Hash {
+    let mut ret = Vec::new();
+
+    for region in regions(base, ours, theirs) {
+        let elems = match region {
+            Region::Resolved(Side::Base, range) => refs(base, range),
+            Region::Resolved(Side::Ours, range) => refs(ours, range),
+            Region::Resolved(Side::Theirs, range) => refs(theirs, range),
+            Region::Conflict(range_base, range_ours, range_theirs) => {
+                ret.push(MergeRegion::Conflict {
+                    base: refs(base, range_base),
+                    ours: refs(ours, range_ours),
+                    theirs: refs(theirs, range_theirs)
+                });
+                continue;
+            }
+        };
+
+        // Both sides deleting the same elements leaves nothing to add.
+        if elems.is_empty() {
+            continue;
+        }
+
+        // Everything between two conflicts is one region, whichever sides it came from.
+        match ret.last_mut() {
+            Some(&mut MergeRegion::Resolved(ref mut resolved)) => resolved.extend(elems),
+            _ => ret.push(MergeRegion::Resolved(elems))
+        }
+    }
+
+    ret
+}
+
+/// Merges the lines of three texts, as split by `split_lines`, like `merge` does.
+pub fn merge_lines<'a>(base: &'a str, ours: &'a str, theirs: &'a str)
+        -> Vec<MergeRegion<&'a str>> {
+    let base = split_lines(base);
+    let ours = split_lines(ours);
+    let theirs = split_lines(theirs);
+
+    merge(&base, &ours, &theirs).into_iter().map(|region| {
+        match region {
+            MergeRegion::Resolved(lines) => MergeRegion::Resolved(derefs(lines)),
+            MergeRegion::Conflict { base, ours, theirs } => MergeRegion::Conflict {
+                base: derefs(base),
+                ours: derefs(ours),
+                theirs: derefs(theirs)
+            }
+        }
+    }).collect()
+}
+
+/// Writes merged lines, with conflicts surrounded by conflict markers in the given style, and
+/// returns how many conflicts were written. Lines are written as-is, so they should keep their
+/// line terminators; a line without one gets a `\n` if a conflict marker follows it.
+///
+/// ```
+/// use patience_diff::{ConflictStyle, MergeLabels};
+///
+/// let merged = patience_diff::merge_lines("a\nb\nc\n", "a\nB\nc\n", "a\nbb\nc\n");
+/// let labels = MergeLabels { ours: "ours", base: "base", theirs: "theirs" };
+///
+/// let mut out = Vec::new();
+/// let conflicts = patience_diff::write_merge(&mut out, &merged, ConflictStyle::Diff3, &labels)
+///     .unwrap();
+///
+/// assert_eq!(conflicts, 1);
+/// assert_eq!(String::from_utf8(out).unwrap(), "\
+/// a
+/// <<<<<<< ours
+/// B
+/// ||||||| base
+/// b
+/// =======
+/// bb
+/// >>>>>>> theirs
+/// c
+/// ");
+/// ```
+#[cfg(feature = "std")]
+pub fn write_merge<W, L>(out: &mut W, regions: &[MergeRegion<L>], style: ConflictStyle,
+                         labels: &MergeLabels) -> io::Result<usize>
+        where W: Write, L: AsRef<[u8]> + Eq {
+    let mut conflicts = 0;
+
+    for region in regions {
+        let (base, ours, theirs) = match *region {
+            MergeRegion::Resolved(ref lines) => {
+                write_lines(out, lines, false)?;
+                continue;
+            },
+            MergeRegion::Conflict { ref base, ref ours, ref theirs } => (base, ours, theirs)
+        };
+
+        let (prefix_len, suffix_len) = if style == ConflictStyle::Diff3 {
+            (0, 0)
+        } else {
+            let prefix_len = ::common_prefix_len(ours, theirs);
+            let suffix_len = ::common_suffix_len(&ours[prefix_len..], &theirs[prefix_len..]);
+            (prefix_len, suffix_len)
+        };
+
+        write_lines(out, &ours[..prefix_len], true)?;
+
+        writeln!(out, "<<<<<<< {}", labels.ours)?;
+        write_lines(out, &ours[prefix_len..ours.len() - suffix_len], true)?;
+        if style != ConflictStyle::Merge {
+            writeln!(out, "||||||| {}", labels.base)?;
+            write_lines(out, base, true)?;
+        }
+        writeln!(out, "=======")?;
+        write_lines(out, &theirs[prefix_len..theirs.len() - suffix_len], true)?;
+        writeln!(out, ">>>>>>> {}", labels.theirs)?;
+
+        write_lines(out, &ours[ours.len() - suffix_len..], false)?;
+        conflicts += 1;
+    }
+
+    Ok(conflicts)
+}
+
+#[cfg(feature = "std")]
+fn write_lines<W, L>(out: &mut W, lines: &[L], terminate: bool) -> io::Result<()>
+        where W: Write, L: AsRef<[u8]> {
+    for line in lines {
+        let line = line.as_ref();
+        out.write_all(line)?;
+
+        if terminate && line.last() != Some(&b'\n') {
+            out.write_all(b"\n")?;
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug)]
+enum Side {
+    Base,
+    Ours,
+    Theirs
+}
+
+/// A region of a merge, as positions in the sequences being merged.
+#[derive(Debug)]
+enum Region {
+    Resolved(Side, Range<usize>),
+    Conflict(Range<usize>, Range<usize>, Range<usize>)
+}
+
+fn regions<T>(base: &[T], ours: &[T], theirs: &[T]) -> Vec<Region> where T: Eq + Hash {
+    let ours_matches = matches(base.len(), ::patience_diff_indices(base, ours));
+    let theirs_matches = matches(base.len(), ::patience_diff_indices(base, theirs));
+
+    let mut ret = Vec::new();
+    let (mut i, mut j, mut k) = (0, 0, 0);
+
+    while i < base.len() || j < ours.len() || k < theirs.len() {
+        // Elements of base that both sides keep, right where each side is at, are stable.
+        let stable_len = (i..base.len())
+            .take_while(|&n| {
+                ours_matches[n] == Some(j + n - i) && theirs_matches[n] == Some(k + n - i)
+            })
+            .count();
+
+        if stable_len != 0 {
+            ret.push(Region::Resolved(Side::Base, i..i + stable_len));
+            i += stable_len;
+            j += stable_len;
+            k += stable_len;
+            continue;
+        }
+
+        // Otherwise, everything up to the next element of base that both sides keep is unstable.
+        let (next_i, next_j, next_k) = (i..base.len())
+            .filter_map(|n| {
+                match (ours_matches[n], theirs_matches[n]) {
+                    (Some(next_j), Some(next_k)) => Some((n, next_j, next_k)),
+                    _ => None
+                }
+            })
+            .next()
+            .unwrap_or((base.len(), ours.len(), theirs.len()));
+
+        let chunk_base = &base[i..next_i];
+        let chunk_ours = &ours[j..next_j];
+        let chunk_theirs = &theirs[k..next_k];
+
+        if chunk_ours == chunk_base {
+            ret.push(Region::Resolved(Side::Theirs, k..next_k));
+        } else if chunk_theirs == chunk_base || chunk_ours == chunk_theirs {
+            ret.push(Region::Resolved(Side::Ours, j..next_j));
+        } else {
+            ret.push(Region::Conflict(i..next_i, j..next_j, k..next_k));
+        }
+
+        i = next_i;
+        j = next_j;
+        k = next_k;
+    }
+
+    ret
+}
+
+/// For every element of `base`, where it is kept in the other side of `diff`, if at all.
+fn matches(base_len: usize, diff: Vec<DiffComponent<usize>>) -> Vec<Option<usize>> {
+    let mut ret = vec![None; base_len];
+    for component in diff {
+        if let DiffComponent::Unchanged(i, j) = component {
+            ret[i] = Some(j);
+        }
+    }
+
+    ret
+}
+
+fn refs<T>(elems: &[T], range: Range<usize>) -> Vec<&T> {
+    elems[range].iter().collect()
+}
+
+fn derefs<'a>(lines: Vec<&&'a str>) -> Vec<&'a str> {
+    lines.into_iter().cloned().collect()
+}
+
+#[test]
+fn test_merge_lines() {
+    let base = "a\nb\nc\nd\ne\n";
+    let ours = "a\nb\nX\nd\ne\nf\n";
+    let theirs = "z\na\nb\nY\nd\ne\n";
+
+    assert_eq!(merge_lines(base, ours, theirs), vec![
+        MergeRegion::Resolved(vec!["z\n", "a\n", "b\n"]),
+        MergeRegion::Conflict { base: vec!["c\n"], ours: vec!["X\n"], theirs: vec!["Y\n"] },
+        MergeRegion::Resolved(vec!["d\n", "e\n", "f\n"])
+    ]);
+
+    assert_eq!(merge_lines(base, ours, ours), vec![
+        MergeRegion::Resolved(vec!["a\n", "b\n", "X\n", "d\n", "e\n", "f\n"])
+    ]);
+
+    // Lines that both sides delete disappear without leaving an empty region behind.
+    assert_eq!(merge_lines(base, "a\nd\ne\n", "a\nd\ne\n"), vec![
+        MergeRegion::Resolved(vec!["a\n", "d\n", "e\n"])
+    ]);
+    assert_eq!(merge_lines(base, "", ""), vec![]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_write_merge_styles() {
+    let merged = merge_lines("1\n2\n3\n", "1\nA\nB\nC\n3\n", "1\nA\nX\nC\n3");
+    let labels = MergeLabels { ours: "HEAD", base: "base", theirs: "topic" };
+    let render = |style| {
+        let mut out = Vec::new();
+        assert_eq!(write_merge(&mut out, &merged, style, &labels).unwrap(), 1);
+        String::from_utf8(out).unwrap()
+    };
+
+    assert_eq!(render(ConflictStyle::Diff3), "\
+1
+<<<<<<< HEAD
+A
+B
+C
+3
+||||||| base
+2
+3
+=======
+A
+X
+C
+3
+>>>>>>> topic
+");
+
+    assert_eq!(render(ConflictStyle::ZealousDiff3), "\
+1
+A
+<<<<<<< HEAD
+B
+C
+3
+||||||| base
+2
+3
+=======
+X
+C
+3
+>>>>>>> topic
+");
+
+    assert_eq!(render(ConflictStyle::Merge), "\
+1
+A
+<<<<<<< HEAD
+B
+C
+3
+=======
+X
+C
+3
+>>>>>>> topic
+");
+}
@@ -0,0 +1,295 @@
+//! Finding blocks that a diff deletes in one place and inserts in another.
+//!
+//! Diffs only keep elements in the order they appear in both sequences, so a block that was moved
+//! shows up as a deletion where it used to be and an unrelated insertion where it is now. This
+//! pairs such deletions and insertions back up.
+
+use alloc::vec::Vec;
+use core::hash::Hash;
+use core::ops::Range;
+
+use {Algorithm, DiffComponent};
+use intern::intern;
+use stats::{DiffStats, diff_stats};
+
+/// How `find_moves` decides what counts as a moved block.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MoveOptions {
+    /// The least number of elements a moved block can have, on both sides. Blocks of a line or
+    /// two, like closing braces or blank lines, are usually deleted and inserted by chance rather
+    /// than moved.
+    pub min_len: usize,
+
+    /// How similar the deleted and inserted block must be, as a ratio like `DiffStats::ratio`. At
+    /// 1, the default, only blocks that are moved as they are count; below 1, blocks that are
+    /// also changed a little while being moved count too.
+    pub similarity: f64
+}
+
+impl Default for MoveOptions {
+    fn default() -> MoveOptions {
+        MoveOptions { min_len: 3, similarity: 1.0 }
+    }
+}
+
+/// A block of elements that a diff deletes from `a[old_start..old_start + old_len]` and inserts
+/// at `b[new_start..new_start + new_len]`. `stats` compares the two: for a block that was moved
+/// without any change, it only has unchanged elements.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Move {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub stats: DiffStats
+}
+
+impl Move {
+    /// Whether the block was moved without any change.
+    pub fn is_exact(&self) -> bool {
+        self.stats.insertions == 0 && self.stats.deletions == 0
+    }
+}
+
+/// Finds the blocks that a diff between `a` and `b`, such as one returned by
+/// `patience_diff_indices`, deletes in one place and inserts in another, sorted by where they are
+/// inserted. A deletion and an insertion that are next to each other, with nothing kept in
+/// between, are a change in place rather than a move, and are never paired.
+///
+/// Blocks that are moved as they are get paired first, longest first for every insertion. Then,
+/// if `options.similarity` is below 1, the runs of deletions and insertions that are left are
+/// paired with the most similar run on the other side. To ignore changes that don't matter, like
+/// indentation when code is moved into another block, `a` and `b` can hold normalized elements.
+///
+/// Pairing blocks that are moved as they are takes time linear in the lengths of `a` and `b`,
+/// plus, for every inserted position, the time to compare it with the deleted positions that
+/// start with the same `min_len` elements; this is only quadratic when the same block is deleted
+/// and inserted many times. Pairing similar runs diffs every pair of runs whose lengths allow them
+/// to be similar enough, so it can be much slower on large diffs.
+///
+/// ```
+/// let a = patience_diff::split_lines("fn a() {\n    1\n}\n\nfn b() {\n    2\n}\n\nfn c() {}\n");
+/// let b = patience_diff::split_lines("fn b() {\n    2\n}\n\nfn c() {}\n\nfn a() {\n    1\n}\n");
+///
+/// let diff = patience_diff::patience_diff_indices(&a, &b);
+/// let moves = patience_diff::find_moves(&a, &b, &diff, &Default::default());
+///
+/// assert_eq!(moves.len(), 1);
+/// assert_eq!((moves[0].old_start, moves[0].new_start, moves[0].new_len), (0, 6, 3));
+/// assert!(moves[0].is_exact());
+/// ```
+pub fn find_moves<T>(a: &[T], b: &[T], diff: &[DiffComponent<usize>], options: &MoveOptions)
+        -> Vec<Move> where T: Eq + Hash {
+    let (ids_a, ids_b, _) = intern(a, b);
+    let min_len = options.min_len.max(1);
+
+    // The run of changes every deleted element of `a` and inserted element of `b` is in, counted
+    // by the unchanged elements before it. Elements that are kept, or already paired, have none.
+    let mut groups_a = vec![None; a.len()];
+    let mut groups_b = vec![None; b.len()];
+    let mut group = 0;
+
+    for component in diff {
+        match *component {
+            DiffComponent::Insertion(j) => groups_b[j] = Some(group),
+            DiffComponent::Unchanged(..) => group += 1,
+            DiffComponent::Deletion(i) => groups_a[i] = Some(group)
+        }
+    }
+
+    // Consecutive positions that are deleted (or inserted) are always in the same run of changes,
+    // since a kept element would come between them on that side. A moved block starts with
+    // `min_len` of them, so deletions are indexed by the IDs of those first `min_len` elements
+    // rather than by the first one only, which repeats far more often (as with `}` lines).
+    let starts_a = block_starts(&groups_a, min_len);
+    let starts_b = block_starts(&groups_b, min_len);
+    let blocks_a: Vec<_> = starts_a.iter().map(|&i| &ids_a[i..i + min_len]).collect();
+    let blocks_b: Vec<_> = starts_b.iter().map(|&j| &ids_b[j..j + min_len]).collect();
+    let (block_ids_a, block_ids_b, block_count) = intern(&blocks_a, &blocks_b);
+
+    let mut deleted = vec![Vec::new(); block_count];
+    for (&i, &id) in starts_a.iter().zip(&block_ids_a) {
+        deleted[id].push(i);
+    }
+
+    let mut ret = Vec::new();
+
+    for (&j, &id) in starts_b.iter().zip(&block_ids_b) {
+        let group = match groups_b[j] {
+            Some(group) => group,
+            None => continue
+        };
+
+        let mut best = (0, 0);
+        for &i in &deleted[id] {
+            if groups_a[i].is_none() || groups_a[i] == Some(group) {
+                continue;
+            }
+
+            let len = (0..).take_while(|&k| {
+                groups_a.get(i + k).is_some_and(Option::is_some) &&
+                    groups_b.get(j + k).is_some_and(Option::is_some) &&
+                    ids_a[i + k] == ids_b[j + k]
+            }).count();
+
+            if len > best.0 {
+                best = (len, i);
+            }
+        }
+
+        let (len, i) = best;
+        if len >= min_len {
+            let stats = DiffStats { unchanged: len, ..DiffStats::default() };
+            pair(i..i + len, j..j + len, stats, &mut groups_a, &mut groups_b, &mut ret);
+        }
+    }
+
+    if options.similarity < 1.0 {
+        let runs_a = runs(&groups_a, min_len);
+        let runs_b = runs(&groups_b, min_len);
+        let mut paired_a = vec![false; runs_a.len()];
+
+        for run_b in runs_b {
+            let group = groups_b[run_b.start];
+            let mut best: Option<(f64, usize, DiffStats)> = None;
+
+            for (k, run_a) in runs_a.iter().enumerate() {
+                if paired_a[k] || groups_a[run_a.start] == group {
+                    continue;
+                }
+
+                // No pair of blocks can be more similar than if the shorter one is all kept.
+                let shortest = run_a.len().min(run_b.len());
+                let bound = (2 * shortest) as f64 / (run_a.len() + run_b.len()) as f64;
+                let to_beat = best.map_or(options.similarity, |(ratio, _, _)| ratio);
+                if bound < to_beat {
+                    continue;
+                }
+
+                let stats = diff_stats(&ids_a[run_a.clone()], &ids_b[run_b.clone()],
+                                       Algorithm::Patience);
+                let ratio = stats.ratio();
+                let is_better = match best {
+                    Some((best, _, _)) => ratio > best,
+                    None => ratio >= options.similarity
+                };
+                if is_better {
+                    best = Some((ratio, k, stats));
+                }
+            }
+
+            if let Some((_, k, stats)) = best {
+                paired_a[k] = true;
+                pair(runs_a[k].clone(), run_b, stats, &mut groups_a, &mut groups_b, &mut ret);
+            }
+        }
+    }
+
+    ret.sort_by_key(|m| m.new_start);
+    ret
+}
+
+/// Records that `a[range_a]` was moved to `b[range_b]`, so that they are not paired again.
+fn pair(range_a: Range<usize>, range_b: Range<usize>, stats: DiffStats,
+        groups_a: &mut [Option<usize>], groups_b: &mut [Option<usize>], ret: &mut Vec<Move>) {
+    ret.push(Move {
+        old_start: range_a.start,
+        old_len: range_a.len(),
+        new_start: range_b.start,
+        new_len: range_b.len(),
+        stats
+    });
+
+    for group in &mut groups_a[range_a] {
+        *group = None;
+    }
+
+    for group in &mut groups_b[range_b] {
+        *group = None;
+    }
+}
+
+/// Finds the runs of at least `min_len` consecutive positions that are in a run of changes.
+fn runs(groups: &[Option<usize>], min_len: usize) -> Vec<Range<usize>> {
+    let mut ret = Vec::new();
+    let mut start = 0;
+
+    for (k, group) in groups.iter().enumerate() {
+        if group.is_none() {
+            start = k + 1;
+        } else if (k + 1 == groups.len() || groups[k + 1].is_none()) && k + 1 - start >= min_len {
+            ret.push(start..k + 1);
+        }
+    }
+
+    ret
+}
+
+/// The positions that start `min_len` elements of the same run in `groups`.
+fn block_starts(groups: &[Option<usize>], min_len: usize) -> Vec<usize> {
+    runs(groups, min_len).into_iter().flat_map(|run| run.start..run.end + 1 - min_len).collect()
+}
+
+#[test]
+fn test_find_moves() {
+    let a = vec!["fn a() {", "    x", "}", "", "fn b() {", "    y", "}", "", "fn c() {}"];
+    let b = vec!["fn b() {", "    y", "}", "", "fn c() {}", "", "fn a() {", "    x", "}"];
+
+    let diff = ::patience_diff_indices(&a, &b);
+    let moves = find_moves(&a, &b, &diff, &MoveOptions::default());
+    assert_eq!(moves, vec![Move {
+        old_start: 0,
+        old_len: 3,
+        new_start: 6,
+        new_len: 3,
+        stats: DiffStats { insertions: 0, deletions: 0, unchanged: 3 }
+    }]);
+
+    // A block that is changed while it moves is only found by allowing it.
+    let c = vec!["fn b() {", "    y", "}", "", "fn c() {}", "", "fn a() {", "    x", "    z", "}"];
+    let diff = ::patience_diff_indices(&a, &c);
+    assert_eq!(find_moves(&a, &c, &diff, &MoveOptions::default()), vec![]);
+
+    // The blank lines around the block are deleted and inserted along with it.
+    let options = MoveOptions { similarity: 0.6, ..MoveOptions::default() };
+    let moves = find_moves(&a, &c, &diff, &options);
+    assert_eq!(moves.len(), 1);
+    assert_eq!((moves[0].old_start, moves[0].old_len), (0, 4));
+    assert_eq!((moves[0].new_start, moves[0].new_len), (5, 5));
+    assert_eq!(moves[0].stats, DiffStats { insertions: 2, deletions: 1, unchanged: 3 });
+    assert!(!moves[0].is_exact());
+
+    // A replacement in place is not a move.
+    let d = vec!["x", "y", "z"];
+    let e = vec!["w", "x", "y", "z"];
+    let replaced = vec![
+        DiffComponent::Deletion(0),
+        DiffComponent::Deletion(1),
+        DiffComponent::Deletion(2),
+        DiffComponent::Insertion(0),
+        DiffComponent::Insertion(1),
+        DiffComponent::Insertion(2),
+        DiffComponent::Insertion(3)
+    ];
+    let options = MoveOptions { min_len: 1, similarity: 0.1 };
+    assert_eq!(find_moves(&d, &e, &replaced, &options), vec![]);
+
+    // Lines like `}` start many deleted and inserted blocks, but only the one that was moved
+    // matches the first `min_len` lines of an inserted block.
+    let mut f = Vec::new();
+    let mut g = vec![String::from("keep")];
+    for k in 0..50 {
+        f.extend(vec![String::from("}"), String::new(), format!("f{}", k)]);
+        g.extend(vec![String::from("}"), String::new(), format!("g{}", k)]);
+    }
+    f.push(String::from("keep"));
+    g.extend(vec![String::from("}"), String::new(), String::from("f7")]);
+
+    let mut diff: Vec<_> = (0..150).map(DiffComponent::Deletion).collect();
+    diff.push(DiffComponent::Unchanged(150, 0));
+    diff.extend((1..154).map(DiffComponent::Insertion));
+    let moves = find_moves(&f, &g, &diff, &MoveOptions::default());
+    assert_eq!(moves.len(), 1);
+    assert_eq!((moves[0].old_start, moves[0].new_start, moves[0].new_len), (21, 151, 3));
+}
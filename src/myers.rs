@@ -0,0 +1,210 @@
+//! Myers' O(ND) diff algorithm, in its linear-space variant: rather than keeping every step of
+//! the search, it finds a "middle snake" of an optimal path, and recursively diffs the ranges
+//! before and after it. See Eugene W. Myers, "An O(ND) Difference Algorithm and Its
+//! Variations" (1986), section 4b.
+
+use alloc::vec::Vec;
+use core::ops::{Index, IndexMut, Range};
+
+use {DiffComponent, Task};
+use budget::Meter;
+
+/// The state Myers' algorithm keeps between the ranges it diffs: the furthest-reaching positions
+/// of the forward and backward searches. They are only ever as large as the largest range diffed
+/// so far, and reused for smaller ones.
+pub struct Myers {
+    vf: V,
+    vb: V
+}
+
+impl Myers {
+    pub fn new() -> Myers {
+        Myers { vf: V::new(0), vb: V::new(0) }
+    }
+
+    /// Diffs `a[range_a]` and `b[range_b]`. The common prefix and suffix, and ranges that are left
+    /// empty on either side, are handled right away. Otherwise, the ranges are split at a middle
+    /// snake, and the two halves are pushed onto `stack` to be diffed in turn. Over all the
+    /// halves, this takes O((N + M) D) time and O(N + M) memory, where N and M are the lengths of
+    /// the ranges and D is the number of insertions and deletions. If `meter` runs out while
+    /// searching for the middle snake, the ranges are replaced as a whole instead.
+    #[allow(clippy::too_many_arguments)]
+    pub fn conquer<T, R>(&mut self, a: &[T], b: &[T], range_a: Range<usize>,
+                         range_b: Range<usize>, meter: &mut Meter, stack: &mut Vec<Task>,
+                         ret: &mut R) where T: Eq, R: Extend<DiffComponent<usize>> {
+        let prefix_len = ::common_prefix_len(&a[range_a.clone()], &b[range_b.clone()]);
+        ::push_unchanged(range_a.start, range_b.start, prefix_len, ret);
+
+        let start_a = range_a.start + prefix_len;
+        let start_b = range_b.start + prefix_len;
+
+        let suffix_len = ::common_suffix_len(&a[start_a..range_a.end], &b[start_b..range_b.end]);
+        let end_a = range_a.end - suffix_len;
+        let end_b = range_b.end - suffix_len;
+
+        if suffix_len != 0 {
+            stack.push(Task::Unchanged(end_a, end_b, suffix_len));
+        }
+
+        let sub_a = &a[start_a..end_a];
+        let sub_b = &b[start_b..end_b];
+
+        let split = if sub_a.is_empty() || sub_b.is_empty() {
+            None
+        } else {
+            let max_d = max_d(sub_a.len(), sub_b.len());
+            if self.vf.max_d() < max_d {
+                self.vf = V::new(max_d);
+                self.vb = V::new(max_d);
+            }
+
+            middle_snake(sub_a, sub_b, &mut self.vf, &mut self.vb, meter)
+        };
+
+        match split {
+            Some((x, y)) => {
+                stack.push(Task::Myers(start_a + x..end_a, start_b + y..end_b));
+                stack.push(Task::Myers(start_a..start_a + x, start_b..start_b + y));
+            },
+            None => ::push_replacement(start_a..end_a, start_b..end_b, ret)
+        }
+    }
+}
+
+/// Finds a point on an optimal path from the start of `a` and `b` to their end, at which the
+/// path can be split into two halves that are searched independently. `a` and `b` must be
+/// non-empty, and must not start or end with the same element. Returns `None` if `meter` runs
+/// out first.
+fn middle_snake<T>(a: &[T], b: &[T], vf: &mut V, vb: &mut V, meter: &mut Meter)
+        -> Option<(usize, usize)> where T: Eq {
+    let n = a.len();
+    let m = b.len();
+    let delta = n as isize - m as isize;
+    let odd = delta & 1 == 1;
+
+    vf[1] = 0;
+    vb[1] = 0;
+
+    for d in 0..max_d(n, m) as isize {
+        // Each of the two searches visits d + 1 diagonals.
+        if !meter.charge(2 * (d as u64 + 1)) {
+            return None;
+        }
+
+        // Extend the furthest-reaching forward paths from the start by one more edit.
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && vf[k - 1] < vf[k + 1]) {
+                vf[k + 1]
+            } else {
+                vf[k - 1] + 1
+            };
+            let y = (x as isize - k) as usize;
+            let (x0, y0) = (x, y);
+
+            if x < n && y < m {
+                x += ::common_prefix_len(&a[x..], &b[y..]);
+            }
+
+            vf[k] = x;
+            if odd && (k - delta).abs() < d && vf[k] + vb[-(k - delta)] >= n {
+                return Some((x0, y0));
+            }
+        }
+
+        // Extend the furthest-reaching backward paths from the end by one more edit.
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && vb[k - 1] < vb[k + 1]) {
+                vb[k + 1]
+            } else {
+                vb[k - 1] + 1
+            };
+            let mut y = (x as isize - k) as usize;
+
+            if x < n && y < m {
+                let advance = ::common_suffix_len(&a[..n - x], &b[..m - y]);
+                x += advance;
+                y += advance;
+            }
+
+            vb[k] = x;
+            if !odd && (k - delta).abs() <= d && vb[k] + vf[-(k - delta)] >= n {
+                return Some((n - x, m - y));
+            }
+        }
+    }
+
+    None
+}
+
+/// The largest number of edits `middle_snake` needs to search through, for inputs of the given
+/// lengths.
+fn max_d(len_a: usize, len_b: usize) -> usize {
+    let len = len_a + len_b;
+    len / 2 + len % 2 + 1
+}
+
+/// The furthest-reaching positions along each diagonal `k`, indexed from `-max_d` to `max_d`.
+struct V {
+    offset: isize,
+    v: Vec<usize>
+}
+
+impl V {
+    fn new(max_d: usize) -> V {
+        V { offset: max_d as isize, v: vec![0; 2 * max_d] }
+    }
+
+    fn max_d(&self) -> usize {
+        self.offset as usize
+    }
+}
+
+impl Index<isize> for V {
+    type Output = usize;
+
+    fn index(&self, k: isize) -> &usize {
+        &self.v[(k + self.offset) as usize]
+    }
+}
+
+impl IndexMut<isize> for V {
+    fn index_mut(&mut self, k: isize) -> &mut usize {
+        &mut self.v[(k + self.offset) as usize]
+    }
+}
+
+#[test]
+fn test_myers_diff() {
+    let a: Vec<_> = "ABCABBA".chars().collect();
+    let b: Vec<_> = "CBABAC".chars().collect();
+
+    // Neither sequence has an element that is unique in both, so this is diffed entirely with
+    // Myers' algorithm.
+    let ret = ::patience_diff_indices(&a, &b);
+
+    // The edit script has the minimal five insertions and deletions from the paper's example.
+    let edits = ret.iter().filter(|c| !matches!(**c, DiffComponent::Unchanged(..))).count();
+    assert_eq!(edits, 5);
+    let diff: Vec<_> = ret.iter().map(|c| {
+        match *c {
+            DiffComponent::Insertion(j) => DiffComponent::Insertion(&b[j]),
+            DiffComponent::Unchanged(i, j) => DiffComponent::Unchanged(&a[i], &b[j]),
+            DiffComponent::Deletion(i) => DiffComponent::Deletion(&a[i])
+        }
+    }).collect();
+    assert_eq!(::apply(&a, &diff), Ok(b.clone()));
+}
+
+#[test]
+fn test_myers_diff_large() {
+    // Without any unique elements to anchor on, a quadratic longest common subsequence table for
+    // these inputs would have billions of entries.
+    let a: Vec<_> = (0..50_000).map(|i| i % 3).collect();
+    let mut b = a.clone();
+    for i in (0..b.len()).step_by(1000) {
+        b[i] = (b[i] + 1) % 3;
+    }
+
+    let diff = ::patience_diff(&a, &b);
+    assert_eq!(::apply(&a, &diff), Ok(b.clone()));
+}
@@ -0,0 +1,447 @@
+#[cfg(feature = "std")]
+use std::error::Error;
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use DiffComponent;
+use hunk::Hunk;
+use lines::split_lines;
+
+/// The changes to a single file in a unified diff.
+///
+/// `header` holds the lines that introduce the file, without their line terminators: the `diff`
+/// command line (such as `diff --git a/file b/file`) and, for git diffs, extended headers like
+/// `index`, `new file mode` or `rename from`. `old_name` and `new_name` come from the `---` and
+/// `+++` lines, with any timestamp removed; they are `None` for changes (like a mode change)
+/// that git describes without them.
+///
+/// The lines in `hunks` keep their line terminators, like the output of `diff_lines`, except for
+/// lines followed by a `\ No newline at end of file` marker.
+///
+/// With the `serde` feature, a `FilePatch` can be serialized but not deserialized, since its
+/// lines borrow from the parsed text; its `hunks` can be read back as `Hunk<String>`s.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FilePatch<'a> {
+    pub header: Vec<&'a str>,
+    pub old_name: Option<&'a str>,
+    pub new_name: Option<&'a str>,
+    pub hunks: Vec<Hunk<&'a str>>
+}
+
+/// An error encountered while parsing a unified diff. `line` and `column` are counted from 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseErrorKind
+}
+
+/// What went wrong while parsing a unified diff.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// A `---` line that starts a file is not followed by a `+++` line.
+    MissingNewName,
+
+    /// A line starting with `@@ ` is not a valid `@@ -a,b +c,d @@` hunk header.
+    InvalidHunkHeader,
+
+    /// A line inside a hunk does not start with ` `, `-`, `+` or `\`.
+    UnexpectedLine,
+
+    /// A hunk has more lines of some kind than its header says.
+    LineCountMismatch,
+
+    /// A `\ No newline at end of file` marker does not follow a line of the hunk.
+    MisplacedMarker,
+
+    /// The input ends in the middle of a hunk.
+    UnexpectedEnd
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match self.kind {
+            ParseErrorKind::MissingNewName => "expected a `+++` line",
+            ParseErrorKind::InvalidHunkHeader => "invalid hunk header",
+            ParseErrorKind::UnexpectedLine => "unexpected line in hunk",
+            ParseErrorKind::LineCountMismatch => "hunk has more lines than its header says",
+            ParseErrorKind::MisplacedMarker => "misplaced `\\ No newline at end of file`",
+            ParseErrorKind::UnexpectedEnd => "unexpected end of input in hunk"
+        };
+
+        write!(f, "line {}, column {}: {}", self.line, self.column, description)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for ParseError {}
+
+/// Parses a unified diff, as written by `write_unified`, `diff -u` or `git diff`, into the
+/// changes it makes to each file. Lines that are not part of any file's changes, like a commit
+/// message in front of the diff, are skipped.
+///
+/// ```
+/// use patience_diff::DiffComponent;
+///
+/// let files = patience_diff::parse_patch("\
+/// --- a.txt
+/// +++ b.txt
+/// @@ -1,2 +1,2 @@
+///  a
+/// -b
+/// +c
+/// \\ No newline at end of file
+/// ").unwrap();
+///
+/// assert_eq!(files.len(), 1);
+/// assert_eq!(files[0].old_name, Some("a.txt"));
+/// assert_eq!(files[0].new_name, Some("b.txt"));
+/// assert_eq!(files[0].hunks[0].components, vec![
+///     DiffComponent::Unchanged("a\n", "a\n"),
+///     DiffComponent::Deletion("b\n"),
+///     DiffComponent::Insertion("c")
+/// ]);
+/// ```
+pub fn parse_patch(text: &str) -> Result<Vec<FilePatch<'_>>, ParseError> {
+    let lines = split_lines(text);
+    let mut parser = Parser { lines: &lines, pos: 0 };
+    let mut files = Vec::new();
+
+    while let Some(line) = parser.peek(0) {
+        let starts_file = line.starts_with("diff ") || (line.starts_with("--- ") &&
+            parser.peek(1).is_some_and(|next| next.starts_with("+++ ")));
+
+        if starts_file {
+            files.push(parser.file()?);
+        } else {
+            parser.pos += 1;
+        }
+    }
+
+    Ok(files)
+}
+
+struct Parser<'a, 'b> {
+    lines: &'b [&'a str],
+    pos: usize
+}
+
+impl<'a, 'b> Parser<'a, 'b> {
+    fn peek(&self, ahead: usize) -> Option<&'a str> {
+        self.lines.get(self.pos + ahead).cloned()
+    }
+
+    fn error(&self, column: usize, kind: ParseErrorKind) -> ParseError {
+        ParseError { line: self.pos + 1, column, kind }
+    }
+
+    fn file(&mut self) -> Result<FilePatch<'a>, ParseError> {
+        let mut header = Vec::new();
+        if let Some(line) = self.peek(0).filter(|line| line.starts_with("diff ")) {
+            header.push(trim_newline(line));
+            self.pos += 1;
+
+            while let Some(line) = self.peek(0) {
+                if line.starts_with("--- ") || line.starts_with("@@ ") ||
+                        line.starts_with("diff ") {
+                    break;
+                }
+
+                header.push(trim_newline(line));
+                self.pos += 1;
+            }
+        }
+
+        let mut old_name = None;
+        let mut new_name = None;
+        if let Some(line) = self.peek(0).filter(|line| line.starts_with("--- ")) {
+            old_name = Some(file_name(&line[4..]));
+            self.pos += 1;
+
+            match self.peek(0) {
+                Some(line) if line.starts_with("+++ ") => new_name = Some(file_name(&line[4..])),
+                _ => return Err(self.error(1, ParseErrorKind::MissingNewName))
+            }
+            self.pos += 1;
+        }
+
+        let mut hunks = Vec::new();
+        while self.peek(0).is_some_and(|line| line.starts_with("@@ ")) {
+            hunks.push(self.hunk()?);
+        }
+
+        Ok(FilePatch { header, old_name, new_name, hunks })
+    }
+
+    fn hunk(&mut self) -> Result<Hunk<&'a str>, ParseError> {
+        let header = trim_newline(self.lines[self.pos]);
+        let (old_start, old_len, new_start, new_len) = parse_hunk_header(header)
+            .map_err(|column| self.error(column, ParseErrorKind::InvalidHunkHeader))?;
+        self.pos += 1;
+
+        let mut old_left = old_len;
+        let mut new_left = new_len;
+        let mut components: Vec<DiffComponent<&'a str>> = Vec::new();
+
+        loop {
+            let line = match self.peek(0) {
+                Some(line) => line,
+                None if old_left == 0 && new_left == 0 => break,
+                None => return Err(self.error(1, ParseErrorKind::UnexpectedEnd))
+            };
+
+            match line.as_bytes()[0] {
+                b'\\' => {
+                    match components.last_mut() {
+                        Some(DiffComponent::Insertion(line)) |
+                        Some(DiffComponent::Deletion(line)) => {
+                            *line = strip_newline(line);
+                        },
+                        Some(DiffComponent::Unchanged(line_a, line_b)) => {
+                            *line_a = strip_newline(line_a);
+                            *line_b = strip_newline(line_b);
+                        },
+                        None => return Err(self.error(1, ParseErrorKind::MisplacedMarker))
+                    }
+                },
+                _ if old_left == 0 && new_left == 0 => break,
+                b' ' | b'\n' => {
+                    if old_left == 0 || new_left == 0 {
+                        return Err(self.error(1, ParseErrorKind::LineCountMismatch));
+                    }
+
+                    // Some tools strip the trailing space off of empty context lines.
+                    let content = line.strip_prefix(' ').unwrap_or(line);
+                    components.push(DiffComponent::Unchanged(content, content));
+                    old_left -= 1;
+                    new_left -= 1;
+                },
+                b'-' => {
+                    if old_left == 0 {
+                        return Err(self.error(1, ParseErrorKind::LineCountMismatch));
+                    }
+
+                    components.push(DiffComponent::Deletion(&line[1..]));
+                    old_left -= 1;
+                },
+                b'+' => {
+                    if new_left == 0 {
+                        return Err(self.error(1, ParseErrorKind::LineCountMismatch));
+                    }
+
+                    components.push(DiffComponent::Insertion(&line[1..]));
+                    new_left -= 1;
+                },
+                _ => return Err(self.error(1, ParseErrorKind::UnexpectedLine))
+            }
+
+            self.pos += 1;
+        }
+
+        Ok(Hunk {
+            old_start: zero_based(old_start, old_len),
+            old_len,
+            new_start: zero_based(new_start, new_len),
+            new_len,
+            components
+        })
+    }
+}
+
+/// Parses `@@ -a,b +c,d @@`, where either length may be left out, into `(a, b, c, d)`. On
+/// failure, returns the column at which the header stops making sense.
+fn parse_hunk_header(header: &str) -> Result<(usize, usize, usize, usize), usize> {
+    let mut cursor = Cursor { bytes: header.as_bytes(), pos: 0 };
+
+    cursor.eat(b"@@ -")?;
+    let (old_start, old_len) = cursor.range()?;
+    cursor.eat(b" +")?;
+    let (new_start, new_len) = cursor.range()?;
+    cursor.eat(b" @@")?;
+
+    Ok((old_start, old_len, new_start, new_len))
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    pos: usize
+}
+
+impl<'a> Cursor<'a> {
+    fn eat(&mut self, expected: &[u8]) -> Result<(), usize> {
+        for &byte in expected {
+            if self.bytes.get(self.pos) != Some(&byte) {
+                return Err(self.pos + 1);
+            }
+
+            self.pos += 1;
+        }
+
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<usize, usize> {
+        let start = self.pos;
+        let mut value: usize = 0;
+
+        while let Some(digit) = self.bytes.get(self.pos).filter(|byte| byte.is_ascii_digit()) {
+            value = value.checked_mul(10)
+                .and_then(|value| value.checked_add((digit - b'0') as usize))
+                .ok_or(start + 1)?;
+            self.pos += 1;
+        }
+
+        if self.pos == start {
+            return Err(start + 1);
+        }
+
+        Ok(value)
+    }
+
+    /// Parses `start` or `start,len`, where a start of 0 is only allowed for empty ranges.
+    fn range(&mut self) -> Result<(usize, usize), usize> {
+        let start_column = self.pos + 1;
+        let start = self.number()?;
+        let len = if self.bytes.get(self.pos) == Some(&b',') {
+            self.pos += 1;
+            self.number()?
+        } else {
+            1
+        };
+
+        if start == 0 && len != 0 {
+            return Err(start_column);
+        }
+
+        Ok((start, len))
+    }
+}
+
+/// Converts a hunk header's position to a zero-based `Hunk` start. Empty ranges are reported at
+/// the line before them, so they need no adjustment.
+fn zero_based(start: usize, len: usize) -> usize {
+    if len == 0 { start } else { start - 1 }
+}
+
+fn file_name(text: &str) -> &str {
+    let name = trim_newline(text);
+    match name.find('\t') {
+        Some(tab) => &name[..tab],
+        None => name
+    }
+}
+
+fn trim_newline(line: &str) -> &str {
+    let line = strip_newline(line);
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+fn strip_newline(line: &str) -> &str {
+    line.strip_suffix('\n').unwrap_or(line)
+}
+
+#[test]
+fn test_parse_git_patch() {
+    let text = "\
+commit message
+
+diff --git a/one.txt b/one.txt
+index 1111111..2222222 100644
+--- a/one.txt\t2020-01-01 00:00:00
++++ b/one.txt
+@@ -1,3 +1,3 @@ fn main() {
+ a
+-b
++c
+
+diff --git a/new.txt b/new.txt
+new file mode 100644
+--- /dev/null
++++ b/new.txt
+@@ -0,0 +1 @@
++x
+\\ No newline at end of file
+diff --git a/run.sh b/run.sh
+old mode 100644
+new mode 100755
+";
+
+    let files = parse_patch(text).unwrap();
+    assert_eq!(files, vec![
+        FilePatch {
+            header: vec!["diff --git a/one.txt b/one.txt", "index 1111111..2222222 100644"],
+            old_name: Some("a/one.txt"),
+            new_name: Some("b/one.txt"),
+            hunks: vec![Hunk {
+                old_start: 0,
+                old_len: 3,
+                new_start: 0,
+                new_len: 3,
+                components: vec![
+                    DiffComponent::Unchanged("a\n", "a\n"),
+                    DiffComponent::Deletion("b\n"),
+                    DiffComponent::Insertion("c\n"),
+                    DiffComponent::Unchanged("\n", "\n")
+                ]
+            }]
+        },
+        FilePatch {
+            header: vec!["diff --git a/new.txt b/new.txt", "new file mode 100644"],
+            old_name: Some("/dev/null"),
+            new_name: Some("b/new.txt"),
+            hunks: vec![Hunk {
+                old_start: 0,
+                old_len: 0,
+                new_start: 0,
+                new_len: 1,
+                components: vec![DiffComponent::Insertion("x")]
+            }]
+        },
+        FilePatch {
+            header: vec!["diff --git a/run.sh b/run.sh", "old mode 100644", "new mode 100755"],
+            old_name: None,
+            new_name: None,
+            hunks: vec![]
+        }
+    ]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_parse_round_trip() {
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12";
+    let new = "1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13";
+    let text = ::unified_diff(old, new, "old", "new", 3);
+
+    let files = parse_patch(&text).unwrap();
+    assert_eq!(files.len(), 1);
+
+    let mut written = Vec::new();
+    ::write_unified(&mut written, "old", "new", &files[0].hunks).unwrap();
+    assert_eq!(String::from_utf8(written).unwrap(), text);
+
+    let applied = ::apply_hunks(&split_lines(old), &files[0].hunks).unwrap();
+    assert_eq!(applied.concat(), new);
+}
+
+#[test]
+fn test_parse_errors() {
+    let error = |text| parse_patch(text).unwrap_err();
+
+    assert_eq!(error("--- a\n+++ b\n@@ -1,2 +1 x @@\n"),
+               ParseError { line: 3, column: 12, kind: ParseErrorKind::InvalidHunkHeader });
+    assert_eq!(error("--- a\n+++ b\n@@ -0,1 +1 @@\n"),
+               ParseError { line: 3, column: 5, kind: ParseErrorKind::InvalidHunkHeader });
+    assert_eq!(error("diff a b\n--- a\nb\n"),
+               ParseError { line: 3, column: 1, kind: ParseErrorKind::MissingNewName });
+    assert_eq!(error("--- a\n+++ b\n@@ -1 +1 @@\n-x\n-y\n"),
+               ParseError { line: 5, column: 1, kind: ParseErrorKind::LineCountMismatch });
+    assert_eq!(error("--- a\n+++ b\n@@ -1,2 +1,2 @@\n x\n*y\n"),
+               ParseError { line: 5, column: 1, kind: ParseErrorKind::UnexpectedLine });
+    assert_eq!(error("--- a\n+++ b\n@@ -1,2 +1,2 @@\n x\n"),
+               ParseError { line: 5, column: 1, kind: ParseErrorKind::UnexpectedEnd });
+    assert_eq!(error("--- a\n+++ b\n@@ -1 +1 @@\n\\ No newline at end of file\n"),
+               ParseError { line: 4, column: 1, kind: ParseErrorKind::MisplacedMarker });
+}
@@ -0,0 +1,242 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use DiffComponent;
+
+/// The units `refine` diffs changed lines in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Granularity {
+    /// Runs of letters, digits and underscores, runs of whitespace, and every other character on
+    /// its own.
+    Word,
+
+    /// Single characters. Bytes that are not valid UTF-8 are units of their own.
+    Char
+}
+
+/// A line of a diff, along with the byte ranges within it that `refine` found to be changed.
+///
+/// The ranges are sorted, do not overlap or touch, and are relative to the start of the line they
+/// belong to: the deleted line for a `Deletion`, and the inserted line for an `Insertion`. They are
+/// always empty for an `Unchanged` line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RefinedLine<L> {
+    pub component: DiffComponent<L>,
+    pub changed: Vec<Range<usize>>
+}
+
+/// The most tokens either side of a block of changed lines can be split into for `refine` to diff
+/// them. Bigger blocks are costly to diff and rarely pair up lines in a useful way.
+const MAX_BLOCK_TOKENS: usize = 10_000;
+
+/// Refines a line diff, such as one returned by `diff_lines`, by finding which parts of the
+/// changed lines actually changed.
+///
+/// Every block of consecutive deleted and inserted lines is split into words or characters, and
+/// the deleted and inserted parts are diffed with a patience diff. The parts that diff deletes or
+/// inserts are the changed ranges of the lines they come from. Blocks that only delete or only
+/// insert lines have nothing to compare with, so their lines have no changed ranges. Neither do
+/// the lines of blocks where either side splits into more than 10,000 parts, which would be too
+/// costly to diff.
+///
+/// ```
+/// use patience_diff::{DiffComponent, Granularity};
+///
+/// let diff = patience_diff::diff_lines("let x = 1;\n", "let y = 1;\n");
+/// let refined = patience_diff::refine(&diff, Granularity::Word);
+///
+/// assert_eq!(refined.len(), 2);
+/// for line in refined {
+///     match line.component {
+///         DiffComponent::Deletion(old) => assert_eq!(&old[line.changed[0].clone()], "x"),
+///         DiffComponent::Insertion(new) => assert_eq!(&new[line.changed[0].clone()], "y"),
+///         DiffComponent::Unchanged(..) => unreachable!()
+///     }
+/// }
+/// ```
+pub fn refine<L>(diff: &[DiffComponent<L>], granularity: Granularity) -> Vec<RefinedLine<L>>
+        where L: AsRef<[u8]> + Clone {
+    let mut ret: Vec<_> = diff.iter().map(|component| {
+        RefinedLine { component: component.clone(), changed: Vec::new() }
+    }).collect();
+
+    let mut start = 0;
+    while start < diff.len() {
+        let end = diff[start..].iter()
+            .position(|c| matches!(*c, DiffComponent::Unchanged(..)))
+            .map_or(diff.len(), |len| start + len);
+
+        if start == end {
+            start += 1;
+            continue;
+        }
+
+        let mut old = Vec::new();
+        let mut new = Vec::new();
+        for (index, component) in diff.iter().enumerate().take(end).skip(start) {
+            match *component {
+                DiffComponent::Insertion(ref line) => new.push((index, line.as_ref())),
+                DiffComponent::Deletion(ref line) => old.push((index, line.as_ref())),
+                DiffComponent::Unchanged(..) => unreachable!()
+            }
+        }
+
+        if !old.is_empty() && !new.is_empty() {
+            refine_block(&old, &new, granularity, &mut ret);
+        }
+
+        start = end;
+    }
+
+    ret
+}
+
+/// Diffs the parts of a block of deleted lines and a block of inserted lines, given along with
+/// their positions in `ret`, and sets the changed ranges of those lines.
+fn refine_block<L>(old: &[(usize, &[u8])], new: &[(usize, &[u8])], granularity: Granularity,
+                   ret: &mut [RefinedLine<L>]) {
+    let tokens_old = tokens(old, granularity);
+    let tokens_new = tokens(new, granularity);
+    if tokens_old.len() > MAX_BLOCK_TOKENS || tokens_new.len() > MAX_BLOCK_TOKENS {
+        return;
+    }
+
+    let slices_old: Vec<_> = tokens_old.iter().map(|&(line, ref range)| {
+        &old[line].1[range.clone()]
+    }).collect();
+    let slices_new: Vec<_> = tokens_new.iter().map(|&(line, ref range)| {
+        &new[line].1[range.clone()]
+    }).collect();
+
+    for component in ::patience_diff_indices(&slices_old, &slices_new) {
+        let (lines, &(line, ref range)) = match component {
+            DiffComponent::Insertion(j) => (new, &tokens_new[j]),
+            DiffComponent::Unchanged(..) => continue,
+            DiffComponent::Deletion(i) => (old, &tokens_old[i])
+        };
+
+        push_range(&mut ret[lines[line].0].changed, range);
+    }
+}
+
+/// Adds a range to the changed ranges of a line, merging it with the previous range if they
+/// touch.
+fn push_range(ranges: &mut Vec<Range<usize>>, range: &Range<usize>) {
+    match ranges.last_mut() {
+        Some(last) if last.end == range.start => last.end = range.end,
+        _ => ranges.push(range.clone())
+    }
+}
+
+/// Splits every line into tokens, as pairs of the line's position in `lines` and the token's
+/// byte range within it. Tokens never span lines.
+fn tokens(lines: &[(usize, &[u8])], granularity: Granularity) -> Vec<(usize, Range<usize>)> {
+    let mut ret = Vec::new();
+
+    for (line, &(_, text)) in lines.iter().enumerate() {
+        let mut start = 0;
+        while start < text.len() {
+            let len = match granularity {
+                Granularity::Word => word_len(&text[start..]),
+                Granularity::Char => char_len(&text[start..])
+            };
+
+            ret.push((line, start..start + len));
+            start += len;
+        }
+    }
+
+    ret
+}
+
+/// The length of the word `text` starts with. Non-ASCII characters count as letters, so words in
+/// other scripts are kept together.
+fn word_len(text: &[u8]) -> usize {
+    let is_word = |byte: u8| byte.is_ascii_alphanumeric() || byte == b'_' || byte >= 0x80;
+    let is_space = |byte: u8| byte == b' ' || byte == b'\t';
+
+    let first = text[0];
+    let same_kind = if is_word(first) {
+        text.iter().take_while(|&&byte| is_word(byte)).count()
+    } else if is_space(first) {
+        text.iter().take_while(|&&byte| is_space(byte)).count()
+    } else {
+        0
+    };
+
+    same_kind.max(char_len(text))
+}
+
+/// The length of the character `text` starts with, as UTF-8.
+fn char_len(text: &[u8]) -> usize {
+    let continuation = text[1..].iter().take_while(|&&byte| byte & 0xc0 == 0x80).count();
+    let expected = match text[0] {
+        0xc0..=0xdf => 1,
+        0xe0..=0xef => 2,
+        0xf0..=0xf7 => 3,
+        _ => 0
+    };
+
+    1 + continuation.min(expected)
+}
+
+#[test]
+fn test_refine() {
+    let old = "same\nfoo(bar, baz);\nremoved\n";
+    let new = "same\nfoo(bar, qux);\nadded line\nsame\n";
+
+    let diff = ::diff_lines(old, new);
+    let changed: Vec<_> = refine(&diff, Granularity::Word).into_iter()
+        .map(|line| (line.component, ranges(&line.changed)))
+        .collect();
+
+    assert_eq!(changed, vec![
+        (DiffComponent::Unchanged("same\n", "same\n"), vec![]),
+        (DiffComponent::Deletion("foo(bar, baz);\n"), vec![(9, 12)]),
+        (DiffComponent::Deletion("removed\n"), vec![(0, 7)]),
+        (DiffComponent::Insertion("foo(bar, qux);\n"), vec![(9, 12)]),
+        (DiffComponent::Insertion("added line\n"), vec![(0, 11)]),
+        (DiffComponent::Insertion("same\n"), vec![(0, 4)])
+    ]);
+}
+
+#[test]
+fn test_refine_chars() {
+    let diff = vec![DiffComponent::Deletion("naïve\n"), DiffComponent::Insertion("naive\n")];
+    let refined = refine(&diff, Granularity::Char);
+    assert_eq!(ranges(&refined[0].changed), vec![(2, 4)]);
+    assert_eq!(ranges(&refined[1].changed), vec![(2, 3)]);
+
+    let refined = refine(&diff, Granularity::Word);
+    assert_eq!(ranges(&refined[0].changed), vec![(0, 6)]);
+    assert_eq!(ranges(&refined[1].changed), vec![(0, 5)]);
+
+    let diff = vec![
+        DiffComponent::Deletion(&b"\xff\xfe"[..]),
+        DiffComponent::Insertion(&b"\xff"[..])
+    ];
+    assert_eq!(ranges(&refine(&diff, Granularity::Char)[0].changed), vec![(1, 2)]);
+}
+
+#[test]
+fn test_refine_large_block() {
+    let words = "word ".repeat(MAX_BLOCK_TOKENS / 2);
+    let diff = vec![
+        DiffComponent::Deletion(&words[..]),
+        DiffComponent::Deletion("x\n"),
+        DiffComponent::Insertion("y\n")
+    ];
+
+    let refined = refine(&diff, Granularity::Word);
+    assert!(refined.iter().all(|line| line.changed.is_empty()));
+
+    let refined = refine(&diff[1..], Granularity::Word);
+    assert_eq!(ranges(&refined[0].changed), vec![(0, 1)]);
+}
+
+#[cfg(test)]
+fn ranges(ranges: &[Range<usize>]) -> Vec<(usize, usize)> {
+    ranges.iter().map(|range| (range.start, range.end)).collect()
+}
@@ -0,0 +1,97 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+/// A sequence that can be diffed in place with `diff_sequences`: it has a length, and gives out
+/// its elements by position.
+///
+/// This is implemented for references to slices, `Vec`s and `VecDeque`s, giving out references
+/// to their elements. Containers that don't store their elements as such, like ropes or indexes
+/// of lines into a larger text, can give out views of them instead.
+///
+/// ```
+/// use patience_diff::{Algorithm, DiffComponent, Sequence};
+///
+/// /// The lines of a text, as the positions where they start.
+/// struct Lines<'a> {
+///     text: &'a str,
+///     starts: Vec<usize>
+/// }
+///
+/// impl<'a, 'b> Sequence for &'b Lines<'a> {
+///     type Item = &'a str;
+///
+///     fn len(&self) -> usize {
+///         self.starts.len() - 1
+///     }
+///
+///     fn item(&self, index: usize) -> &'a str {
+///         &self.text[self.starts[index]..self.starts[index + 1]]
+///     }
+/// }
+///
+/// let old = Lines { text: "a\nb\n", starts: vec![0, 2, 4] };
+/// let new = Lines { text: "a\nc\n", starts: vec![0, 2, 4] };
+///
+/// let diff = patience_diff::diff_sequences(&old, &new, Algorithm::Patience);
+/// assert_eq!(diff[0], DiffComponent::Unchanged(0, 0));
+/// assert_eq!(diff.len(), 3);
+/// ```
+pub trait Sequence {
+    /// The elements, or views of the elements, of the sequence. These are what is hashed and
+    /// compared to diff sequences.
+    type Item;
+
+    fn len(&self) -> usize;
+
+    /// Returns the element at `index`, which is less than `len()`.
+    fn item(&self, index: usize) -> Self::Item;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<'a, T> Sequence for &'a [T] {
+    type Item = &'a T;
+
+    fn len(&self) -> usize {
+        <[T]>::len(self)
+    }
+
+    fn item(&self, index: usize) -> &'a T {
+        &self[index]
+    }
+}
+
+impl<'a, T> Sequence for &'a Vec<T> {
+    type Item = &'a T;
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn item(&self, index: usize) -> &'a T {
+        &self[index]
+    }
+}
+
+impl<'a, T> Sequence for &'a VecDeque<T> {
+    type Item = &'a T;
+
+    fn len(&self) -> usize {
+        VecDeque::len(self)
+    }
+
+    fn item(&self, index: usize) -> &'a T {
+        &self[index]
+    }
+}
+
+#[test]
+fn test_diff_sequences() {
+    let a: VecDeque<_> = "AaaxZ".chars().collect();
+    let b: Vec<_> = "AxaaZ".chars().collect();
+
+    let diff = ::diff_sequences(&a, &b[..], ::Algorithm::Patience);
+    assert_eq!(diff, ::patience_diff_indices(&Vec::from(a), &b));
+}
@@ -0,0 +1,470 @@
+//! Sliding blocks of changes to better positions, as git's `xdl_change_compact` does.
+//!
+//! A block of deleted (or inserted) elements can often be moved up or down without changing what
+//! the diff does: deleting `b, a` from `a, b, a` is the same as deleting `a, b`. Diffs put such
+//! blocks wherever the algorithm happens to leave them, so these passes move them to a consistent
+//! position: next to a change on the other side if possible, or else as far down as possible, or
+//! else, for lines, where the indentation and blank lines around the block suggest it starts and
+//! ends on a boundary between blocks of code.
+
+use alloc::vec::Vec;
+use core::ops::Add;
+
+use DiffComponent;
+
+/// Lines with this much indentation or more are considered to have this much.
+const MAX_INDENT: usize = 200;
+
+/// Runs of blank lines longer than this are considered to be this long.
+const MAX_BLANKS: usize = 20;
+
+/// The indent heuristic only considers this many positions of a block.
+const MAX_SLIDING: usize = 100;
+
+// The weights of the indent heuristic, as tuned by git on a corpus of human-reviewed diffs.
+const START_OF_FILE_PENALTY: i32 = 1;
+const END_OF_FILE_PENALTY: i32 = 21;
+const TOTAL_BLANK_WEIGHT: i32 = -30;
+const POST_BLANK_WEIGHT: i32 = 6;
+const RELATIVE_INDENT_PENALTY: i32 = -4;
+const RELATIVE_INDENT_WITH_BLANK_PENALTY: i32 = 10;
+const RELATIVE_OUTDENT_PENALTY: i32 = 24;
+const RELATIVE_OUTDENT_WITH_BLANK_PENALTY: i32 = 17;
+const RELATIVE_DEDENT_PENALTY: i32 = 23;
+const RELATIVE_DEDENT_WITH_BLANK_PENALTY: i32 = 17;
+const INDENT_WEIGHT: i32 = 60;
+
+/// Slides the blocks of deletions and insertions of a diff between `a` and `b`, such as one
+/// returned by `patience_diff_indices`, so that they line up with a change on the other side
+/// where possible, or else are as far down as possible. Within every run of changes, the
+/// returned diff deletes before it inserts.
+///
+/// ```
+/// use patience_diff::DiffComponent;
+///
+/// let a = vec!["x", "}", "y"];
+/// let b = vec!["x", "}", "z", "}", "y"];
+///
+/// // Inserting `}, z` after `x` has the same effect as inserting `z, }` after the first `}`.
+/// let diff = vec![
+///     DiffComponent::Unchanged(0, 0),
+///     DiffComponent::Insertion(1),
+///     DiffComponent::Insertion(2),
+///     DiffComponent::Unchanged(1, 3),
+///     DiffComponent::Unchanged(2, 4)
+/// ];
+///
+/// assert_eq!(patience_diff::compact(&a, &b, &diff), vec![
+///     DiffComponent::Unchanged(0, 0),
+///     DiffComponent::Unchanged(1, 1),
+///     DiffComponent::Insertion(2),
+///     DiffComponent::Insertion(3),
+///     DiffComponent::Unchanged(2, 4)
+/// ]);
+/// ```
+pub fn compact<T>(a: &[T], b: &[T], diff: &[DiffComponent<usize>]) -> Vec<DiffComponent<usize>>
+        where T: Eq {
+    slide(a, b, diff, None, None)
+}
+
+/// Slides the blocks of deletions and insertions of a diff between the lines `a` and `b`, like
+/// `compact`, but blocks that can neither line up with a change on the other side nor be moved
+/// without choice are placed using git's indent heuristic: the block is moved to where the blank
+/// lines and indentation around its start and end most look like boundaries between blocks of
+/// code.
+///
+/// ```
+/// use patience_diff::DiffComponent;
+///
+/// let a = patience_diff::split_lines("if a {\n}\n");
+/// let b = patience_diff::split_lines("if a {\n}\nif b {\n}\n");
+///
+/// // Either `}, if b {` or `if b {, }` could have been inserted after `if a {`.
+/// let diff = vec![
+///     DiffComponent::Unchanged(0, 0),
+///     DiffComponent::Insertion(1),
+///     DiffComponent::Insertion(2),
+///     DiffComponent::Unchanged(1, 3)
+/// ];
+///
+/// assert_eq!(patience_diff::compact_lines(&a, &b, &diff), vec![
+///     DiffComponent::Unchanged(0, 0),
+///     DiffComponent::Unchanged(1, 1),
+///     DiffComponent::Insertion(2),
+///     DiffComponent::Insertion(3)
+/// ]);
+/// ```
+pub fn compact_lines<L>(a: &[L], b: &[L], diff: &[DiffComponent<usize>])
+        -> Vec<DiffComponent<usize>> where L: AsRef<[u8]> + Eq {
+    slide(a, b, diff, Some(&indents(a)), Some(&indents(b)))
+}
+
+/// Slides the blocks of a diff between `a` and `b`, using the indentation of their lines, as
+/// returned by `indents`, for the indent heuristic if given.
+pub fn slide<K>(a: &[K], b: &[K], diff: &[DiffComponent<usize>],
+                indents_a: Option<&[Option<usize>]>, indents_b: Option<&[Option<usize>]>)
+        -> Vec<DiffComponent<usize>> where K: Eq {
+    // Whether each element is changed, with an extra unchanged element past the end.
+    let mut changed_a = vec![false; a.len() + 1];
+    let mut changed_b = vec![false; b.len() + 1];
+    for component in diff {
+        match *component {
+            DiffComponent::Insertion(j) => changed_b[j] = true,
+            DiffComponent::Unchanged(..) => {},
+            DiffComponent::Deletion(i) => changed_a[i] = true
+        }
+    }
+
+    slide_side(a, &mut changed_a, &changed_b, indents_a);
+    slide_side(b, &mut changed_b, &changed_a, indents_b);
+
+    let mut ret = Vec::with_capacity(diff.len());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if changed_a[i] {
+            ret.push(DiffComponent::Deletion(i));
+            i += 1;
+        } else if changed_b[j] {
+            ret.push(DiffComponent::Insertion(j));
+            j += 1;
+        } else {
+            ret.push(DiffComponent::Unchanged(i, j));
+            i += 1;
+            j += 1;
+        }
+    }
+
+    ret
+}
+
+/// The indentation of every line, in columns with tab stops every 8 columns, or `None` for lines
+/// that are entirely whitespace.
+pub fn indents<L>(lines: &[L]) -> Vec<Option<usize>> where L: AsRef<[u8]> {
+    lines.iter().map(|line| indent(line.as_ref())).collect()
+}
+
+fn indent(line: &[u8]) -> Option<usize> {
+    let mut ret = 0;
+    for &byte in line {
+        match byte {
+            b' ' => ret += 1,
+            b'\t' => ret += 8 - ret % 8,
+            b'\n' | b'\x0b' | b'\x0c' | b'\r' => {},
+            _ => return Some(ret)
+        }
+
+        if ret >= MAX_INDENT {
+            return Some(MAX_INDENT);
+        }
+    }
+
+    None
+}
+
+/// Slides the groups of changed elements of one side of a diff, keeping track of the groups of
+/// the other side that line up with them.
+fn slide_side<K>(elems: &[K], changed: &mut [bool], other_changed: &[bool],
+                 indents: Option<&[Option<usize>]>) where K: Eq {
+    let mut group = Group::first(changed);
+    let mut other = Group::first(other_changed);
+
+    loop {
+        if !group.is_empty() {
+            let mut earliest_end;
+            let mut end_matching_other;
+
+            // Sliding can merge the group with the ones around it, so repeat until it stops
+            // growing.
+            loop {
+                let len = group.end - group.start;
+                end_matching_other = None;
+
+                while group.slide_up(elems, changed) {
+                    assert!(other.previous(other_changed), "groups out of sync");
+                }
+
+                earliest_end = group.end;
+                if !other.is_empty() {
+                    end_matching_other = Some(group.end);
+                }
+
+                while group.slide_down(elems, changed) {
+                    assert!(other.next(other_changed), "groups out of sync");
+                    if !other.is_empty() {
+                        end_matching_other = Some(group.end);
+                    }
+                }
+
+                if group.end - group.start == len {
+                    break;
+                }
+            }
+
+            if group.end == earliest_end {
+                // The group can't move at all.
+            } else if end_matching_other.is_some() {
+                while other.is_empty() {
+                    assert!(group.slide_up(elems, changed), "matching group disappeared");
+                    assert!(other.previous(other_changed), "groups out of sync");
+                }
+            } else if let Some(indents) = indents {
+                let len = group.end - group.start;
+                let lowest = earliest_end.max((group.end - len).saturating_sub(1))
+                    .max(group.end.saturating_sub(MAX_SLIDING));
+
+                let mut best = None;
+                for end in lowest..group.end + 1 {
+                    let score = Score::split(indents, end) + Score::split(indents, end - len);
+                    match best {
+                        Some((_, ref best_score)) if score.compare(best_score) > 0 => {},
+                        _ => best = Some((end, score))
+                    }
+                }
+
+                let best_end = best.map_or(group.end, |(end, _)| end);
+                while group.end > best_end {
+                    assert!(group.slide_up(elems, changed), "best position unreachable");
+                    assert!(other.previous(other_changed), "groups out of sync");
+                }
+            }
+        }
+
+        if !group.next(changed) {
+            break;
+        }
+        assert!(other.next(other_changed), "groups out of sync");
+    }
+}
+
+/// A maximal run of changed elements on one side of a diff, or the empty run between two
+/// unchanged elements.
+struct Group {
+    start: usize,
+    end: usize
+}
+
+impl Group {
+    fn first(changed: &[bool]) -> Group {
+        Group { start: 0, end: changed.iter().take_while(|&&c| c).count() }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Moves to the next group, if there is one.
+    fn next(&mut self, changed: &[bool]) -> bool {
+        if self.end == changed.len() - 1 {
+            return false;
+        }
+
+        self.start = self.end + 1;
+        self.end = self.start;
+        while changed[self.end] {
+            self.end += 1;
+        }
+
+        true
+    }
+
+    /// Moves to the previous group, if there is one.
+    fn previous(&mut self, changed: &[bool]) -> bool {
+        if self.start == 0 {
+            return false;
+        }
+
+        self.end = self.start - 1;
+        self.start = self.end;
+        while self.start > 0 && changed[self.start - 1] {
+            self.start -= 1;
+        }
+
+        true
+    }
+
+    /// Moves the changes of this group down by one element, if the elements it would swap are
+    /// equal, and merges it with the next group if they then touch.
+    fn slide_down<K>(&mut self, elems: &[K], changed: &mut [bool]) -> bool where K: Eq {
+        if self.end == elems.len() || elems[self.start] != elems[self.end] {
+            return false;
+        }
+
+        changed[self.start] = false;
+        changed[self.end] = true;
+        self.start += 1;
+        self.end += 1;
+        while changed[self.end] {
+            self.end += 1;
+        }
+
+        true
+    }
+
+    /// Moves the changes of this group up by one element, if the elements it would swap are
+    /// equal, and merges it with the previous group if they then touch.
+    fn slide_up<K>(&mut self, elems: &[K], changed: &mut [bool]) -> bool where K: Eq {
+        if self.start == 0 || elems[self.start - 1] != elems[self.end - 1] {
+            return false;
+        }
+
+        self.start -= 1;
+        self.end -= 1;
+        changed[self.start] = true;
+        changed[self.end] = false;
+        while self.start > 0 && changed[self.start - 1] {
+            self.start -= 1;
+        }
+
+        true
+    }
+}
+
+/// How bad a place to start or end a block of changes is, according to the indent heuristic.
+/// Lower is better.
+struct Score {
+    effective_indent: i32,
+    penalty: i32
+}
+
+impl Score {
+    /// Scores a block of changes starting or ending right before line `split`.
+    fn split(indents: &[Option<usize>], split: usize) -> Score {
+        let end_of_file = split >= indents.len();
+        let indent = if end_of_file { None } else { indents[split] };
+
+        let (pre_blank, pre_indent) = blanks(indents[..split].iter().rev());
+        let (post_blank, post_indent) = blanks(indents.iter().skip(split + 1));
+
+        let mut penalty = 0;
+        if pre_indent.is_none() && pre_blank == 0 {
+            penalty += START_OF_FILE_PENALTY;
+        }
+        if end_of_file {
+            penalty += END_OF_FILE_PENALTY;
+        }
+
+        let post_blank = if indent.is_none() { 1 + post_blank } else { 0 };
+        let total_blank = pre_blank + post_blank;
+        penalty += TOTAL_BLANK_WEIGHT * total_blank + POST_BLANK_WEIGHT * post_blank;
+
+        let indent = indent.or(post_indent);
+        let any_blanks = total_blank != 0;
+
+        if let (Some(indent), Some(pre_indent)) = (indent, pre_indent) {
+            if indent > pre_indent {
+                penalty += if any_blanks {
+                    RELATIVE_INDENT_WITH_BLANK_PENALTY
+                } else {
+                    RELATIVE_INDENT_PENALTY
+                };
+            } else if indent < pre_indent {
+                let outdent = post_indent.is_some_and(|post_indent| post_indent > indent);
+                penalty += match (outdent, any_blanks) {
+                    (true, true) => RELATIVE_OUTDENT_WITH_BLANK_PENALTY,
+                    (true, false) => RELATIVE_OUTDENT_PENALTY,
+                    (false, true) => RELATIVE_DEDENT_WITH_BLANK_PENALTY,
+                    (false, false) => RELATIVE_DEDENT_PENALTY
+                };
+            }
+        }
+
+        Score { effective_indent: indent.map_or(-1, |indent| indent as i32), penalty }
+    }
+
+    /// Compares two scores, returning a negative number if `self` is better.
+    fn compare(&self, other: &Score) -> i32 {
+        let indents = (self.effective_indent > other.effective_indent) as i32
+            - (self.effective_indent < other.effective_indent) as i32;
+        INDENT_WEIGHT * indents + self.penalty - other.penalty
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+
+    fn add(self, other: Score) -> Score {
+        Score {
+            effective_indent: self.effective_indent + other.effective_indent,
+            penalty: self.penalty + other.penalty
+        }
+    }
+}
+
+/// Counts the blank lines at the start of `indents`, and returns the count along with the
+/// indentation of the first line that is not blank, if any. Long runs of blank lines are cut
+/// short, and treated as if followed by a line with no indentation.
+fn blanks<'a, I>(indents: I) -> (i32, Option<usize>) where I: Iterator<Item = &'a Option<usize>> {
+    let mut blank = 0;
+    for &indent in indents {
+        if indent.is_some() {
+            return (blank, indent);
+        }
+
+        blank += 1;
+        if blank == MAX_BLANKS as i32 {
+            return (blank, Some(0));
+        }
+    }
+
+    (blank, None)
+}
+
+#[test]
+fn test_compact() {
+    let a = vec![1, 2, 1, 2, 1, 2];
+    let b = vec![1, 2, 9, 1, 2];
+
+    // The deleted `1, 2` could be any of the three, but only one lines up with the inserted `9`.
+    let diff = vec![
+        DiffComponent::Unchanged(0, 0),
+        DiffComponent::Unchanged(1, 1),
+        DiffComponent::Insertion(2),
+        DiffComponent::Unchanged(2, 3),
+        DiffComponent::Unchanged(3, 4),
+        DiffComponent::Deletion(4),
+        DiffComponent::Deletion(5)
+    ];
+
+    assert_eq!(compact(&a, &b, &diff), vec![
+        DiffComponent::Unchanged(0, 0),
+        DiffComponent::Unchanged(1, 1),
+        DiffComponent::Deletion(2),
+        DiffComponent::Deletion(3),
+        DiffComponent::Insertion(2),
+        DiffComponent::Unchanged(4, 3),
+        DiffComponent::Unchanged(5, 4)
+    ]);
+}
+
+#[test]
+fn test_compact_lines() {
+    let old = "fn f() {\n    a();\n\n    // Comment\n    c();\n}\n";
+    let new = "fn f() {\n    a();\n\n    // Comment\n    b();\n\n    // Comment\n    c();\n}\n";
+
+    let a = ::split_lines(old);
+    let b = ::split_lines(new);
+    let diff = ::patience_diff_indices(&a, &b);
+
+    let inserted = |diff: Vec<DiffComponent<usize>>| -> Vec<&str> {
+        diff.into_iter().filter_map(|c| {
+            match c {
+                DiffComponent::Insertion(j) => Some(b[j]),
+                _ => None
+            }
+        }).collect()
+    };
+
+    // As far down as possible, the inserted block splits the second comment from its code.
+    assert_eq!(inserted(compact(&a, &b, &diff)), vec!["    b();\n", "\n", "    // Comment\n"]);
+    assert_eq!(inserted(compact_lines(&a, &b, &diff)),
+               vec!["    // Comment\n", "    b();\n", "\n"]);
+
+    let options = ::LineOptions { indent_heuristic: true, ..::LineOptions::default() };
+    let inserted: Vec<_> = ::diff_lines_with(old, new, &options).into_iter().filter_map(|c| {
+        match c {
+            DiffComponent::Insertion(line) => Some(line),
+            _ => None
+        }
+    }).collect();
+    assert_eq!(inserted, vec!["    // Comment\n", "    b();\n", "\n"]);
+}
@@ -0,0 +1,118 @@
+use core::hash::Hash;
+
+use {Algorithm, Budget, DiffComponent};
+use intern::intern;
+
+/// How many elements a diff inserts, deletes and keeps.
+///
+/// This implements `Extend`, so that components can be counted as they are produced, without
+/// storing them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DiffStats {
+    pub insertions: usize,
+    pub deletions: usize,
+    pub unchanged: usize
+}
+
+impl DiffStats {
+    /// How similar the two sequences are, from 0 (nothing in common) to 1 (equal): twice the number
+    /// of unchanged elements, over the total length of both sequences. This is the same measure as
+    /// Python's `difflib.SequenceMatcher.ratio()`, though the diffs it is computed from can
+    /// differ. Two empty sequences are equal.
+    pub fn ratio(&self) -> f64 {
+        let total = self.insertions + self.deletions + 2 * self.unchanged;
+        if total == 0 {
+            1.0
+        } else {
+            (2 * self.unchanged) as f64 / total as f64
+        }
+    }
+
+    fn add<T>(&mut self, component: &DiffComponent<T>) {
+        match *component {
+            DiffComponent::Insertion(_) => self.insertions += 1,
+            DiffComponent::Unchanged(..) => self.unchanged += 1,
+            DiffComponent::Deletion(_) => self.deletions += 1
+        }
+    }
+}
+
+impl<T> Extend<DiffComponent<T>> for DiffStats {
+    fn extend<I>(&mut self, iter: I) where I: IntoIterator<Item = DiffComponent<T>> {
+        for component in iter {
+            self.add(&component);
+        }
+    }
+}
+
+/// Counts the insertions, deletions and unchanged elements of a diff.
+///
+/// ```
+/// let a: Vec<_> = "AaaxZ".chars().collect();
+/// let b: Vec<_> = "AxaaZ".chars().collect();
+///
+/// let stats = patience_diff::stats(&patience_diff::patience_diff(&a, &b));
+/// assert_eq!((stats.insertions, stats.deletions, stats.unchanged), (2, 2, 3));
+/// assert_eq!(stats.ratio(), 0.6);
+/// ```
+pub fn stats<T>(diff: &[DiffComponent<T>]) -> DiffStats {
+    let mut ret = DiffStats::default();
+    for component in diff {
+        ret.add(component);
+    }
+
+    ret
+}
+
+/// Computes the statistics of the diff between `a` and `b` using the given algorithm, without
+/// keeping the diff itself: this gives the same result as `stats(&diff(a, b, algorithm))`, but the
+/// only memory it needs in proportion to the inputs is for diffing.
+///
+/// ```
+/// use patience_diff::Algorithm;
+///
+/// let old = patience_diff::split_lines("fn main() {\n    println!(\"Hello\");\n}\n");
+/// let new = patience_diff::split_lines("fn main() {\n    println!(\"Hello, world\");\n}\n");
+///
+/// let stats = patience_diff::diff_stats(&old, &new, Algorithm::Patience);
+/// assert!(stats.ratio() > 0.5);
+/// ```
+pub fn diff_stats<T>(a: &[T], b: &[T], algorithm: Algorithm) -> DiffStats where T: Eq + Hash {
+    let (ids_a, ids_b, id_count) = intern(a, b);
+    let budget = Budget::default();
+    ::diff_interned(&ids_a, &ids_b, id_count, algorithm, &budget, DiffStats::default()).0
+}
+
+#[test]
+fn test_stats() {
+    assert_eq!(stats::<usize>(&[]).ratio(), 1.0);
+    assert_eq!(diff_stats::<u8>(&[], &[1, 2], Algorithm::Patience).ratio(), 0.0);
+
+    // The unique x, y and z anchor the diff, and the repeated letters between them go to Myers.
+    let a: Vec<_> = "xABCAByBACzAA".chars().collect();
+    let b: Vec<_> = "xCBABAyACBzA".chars().collect();
+
+    for &algorithm in &[Algorithm::Patience, Algorithm::Histogram] {
+        let counted = diff_stats(&a, &b, algorithm);
+        assert_eq!(counted, stats(&::diff(&a, &b, algorithm)));
+        assert_eq!(counted.deletions + counted.unchanged, a.len());
+        assert_eq!(counted.insertions + counted.unchanged, b.len());
+        assert!(counted.unchanged > 3 && counted.insertions > 0 && counted.deletions > 0);
+    }
+
+    // A diff that only deletes has nothing in common between the two sides.
+    let a = vec!["x", "y", "z"];
+    let b: Vec<&str> = vec![];
+    let deleted = DiffStats { insertions: 0, deletions: 3, unchanged: 0 };
+
+    for &algorithm in &[Algorithm::Patience, Algorithm::Histogram] {
+        assert_eq!(stats(&::diff(&a, &b, algorithm)), deleted);
+        assert_eq!(diff_stats(&a, &b, algorithm), deleted);
+    }
+    assert_eq!(deleted.ratio(), 0.0);
+
+    let mut counted = DiffStats::default();
+    counted.extend(::patience_diff_indices(&b, &a));
+    assert_eq!(counted, DiffStats { insertions: 3, deletions: 0, unchanged: 0 });
+}
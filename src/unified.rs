@@ -0,0 +1,161 @@
+use std::fmt;
+use std::io::{self, Write};
+
+use DiffComponent;
+use hunk::{Hunk, hunks};
+use lines::diff_lines;
+
+/// Writes hunks in unified diff format: a `---`/`+++` header naming the two sides, followed by an
+/// `@@ -a,b +c,d @@` header and the prefixed lines of every hunk.
+///
+/// Lines are written as-is, so they should keep their line terminators. A line that does not end
+/// in `\n` is taken to be the last line of its file, and is followed by a `\ No newline at end of
+/// file` marker.
+pub fn write_unified<W, L>(out: &mut W, old_name: &str, new_name: &str, hunks: &[Hunk<L>])
+        -> io::Result<()> where W: Write, L: AsRef<[u8]> {
+    writeln!(out, "--- {}", old_name)?;
+    writeln!(out, "+++ {}", new_name)?;
+
+    for hunk in hunks {
+        write_hunk(out, hunk)?;
+    }
+
+    Ok(())
+}
+
+/// Writes a single hunk in unified diff format, starting with its `@@ -a,b +c,d @@` header.
+pub fn write_hunk<W, L>(out: &mut W, hunk: &Hunk<L>) -> io::Result<()>
+        where W: Write, L: AsRef<[u8]> {
+    writeln!(out, "@@ -{} +{} @@",
+             HunkRange(hunk.old_start, hunk.old_len),
+             HunkRange(hunk.new_start, hunk.new_len))?;
+
+    // Within a run of changes, all deletions are written before all insertions, regardless of
+    // how they are interleaved in the diff.
+    let mut insertions = Vec::new();
+    for component in &hunk.components {
+        match *component {
+            DiffComponent::Insertion(ref line) => insertions.push(line.as_ref()),
+            DiffComponent::Unchanged(ref line, _) => {
+                for inserted in insertions.drain(..) {
+                    write_line(out, b"+", inserted)?;
+                }
+                write_line(out, b" ", line.as_ref())?;
+            },
+            DiffComponent::Deletion(ref line) => write_line(out, b"-", line.as_ref())?
+        }
+    }
+
+    for inserted in insertions {
+        write_line(out, b"+", inserted)?;
+    }
+
+    Ok(())
+}
+
+/// Computes the patience diff between the lines of `old` and `new` and renders it as a unified
+/// diff with `context` lines of context around each change. Returns an empty string if the two
+/// texts are identical.
+///
+/// ```
+/// let old = "a\nb\nc\n";
+/// let new = "a\nB\nc";
+///
+/// let diff = patience_diff::unified_diff(old, new, "old.txt", "new.txt", 3);
+/// assert_eq!(diff, "\
+/// --- old.txt
+/// +++ new.txt
+/// @@ -1,3 +1,3 @@
+///  a
+/// -b
+/// -c
+/// +B
+/// +c
+/// \\ No newline at end of file
+/// ");
+/// ```
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str, context: usize)
+        -> String {
+    let diff = diff_lines(old, new);
+    let hunks = hunks(&diff, context);
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    let mut out = Vec::new();
+    write_unified(&mut out, old_name, new_name, &hunks)
+        .expect("writing to a Vec cannot fail");
+
+    String::from_utf8(out).expect("diff of two strs is valid UTF-8")
+}
+
+/// Writes `line` after `prefix`, followed by a `\ No newline at end of file` marker if it is the
+/// last line of a file without a final newline.
+pub fn write_line<W>(out: &mut W, prefix: &[u8], line: &[u8]) -> io::Result<()> where W: Write {
+    out.write_all(prefix)?;
+    out.write_all(line)?;
+
+    if line.last() != Some(&b'\n') {
+        out.write_all(b"\n\\ No newline at end of file\n")?;
+    }
+
+    Ok(())
+}
+
+/// Formats one side of a hunk header. Positions are one-based, except that an empty range is
+/// reported at the line it follows, and a length of one is left implicit.
+struct HunkRange(usize, usize);
+
+impl fmt::Display for HunkRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HunkRange(start, 0) => write!(f, "{},0", start),
+            HunkRange(start, 1) => write!(f, "{}", start + 1),
+            HunkRange(start, len) => write!(f, "{},{}", start + 1, len)
+        }
+    }
+}
+
+#[test]
+fn test_unified_diff() {
+    assert_eq!(unified_diff("a\nb\n", "a\nb\n", "a", "b", 3), "");
+
+    assert_eq!(unified_diff("", "x\ny\n", "/dev/null", "b/new", 3), "\
+--- /dev/null
++++ b/new
+@@ -0,0 +1,2 @@
++x
++y
+");
+
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+    let new = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n";
+    assert_eq!(unified_diff(old, new, "a", "b", 2), "\
+--- a
++++ b
+@@ -8,2 +8,3 @@
+ 8
+ 9
++10
+");
+}
+
+#[test]
+fn test_write_hunk_no_newline() {
+    let hunk = Hunk {
+        old_start: 0,
+        old_len: 1,
+        new_start: 0,
+        new_len: 1,
+        components: vec![DiffComponent::Deletion("x"), DiffComponent::Insertion("x\n")]
+    };
+
+    let mut out = Vec::new();
+    write_hunk(&mut out, &hunk).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "\
+@@ -1 +1 @@
+-x
+\\ No newline at end of file
++x
+");
+}